@@ -0,0 +1,207 @@
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use macroquad::texture::Image;
+
+use crate::{FileWatcher, Material, Mesh, Scene, Skybox, material::load_texture};
+
+// Loads each file once and hands out shared handles to it -> Placing the same tree 200 times
+// keeps a single copy of its mesh and texture in memory
+#[derive(Default)]
+pub struct Assets {
+    // Keyed by whether faces were read with texture coordinates too, as that changes the mesh
+    meshes: HashMap<(PathBuf, bool), Rc<Mesh>>,
+    textures: HashMap<PathBuf, Rc<Image>>,
+    materials: HashMap<PathBuf, Rc<Material>>,
+    cube: Option<Rc<Mesh>>,
+    // Every file above, so they can be loaded again when they change on disk
+    watcher: FileWatcher,
+}
+
+impl Assets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // OBJ or PLY, going by the extension
+    pub fn mesh(&mut self, path: &Path, has_texture: bool) -> io::Result<Rc<Mesh>> {
+        let key = (path.to_path_buf(), has_texture);
+        if let Some(mesh) = self.meshes.get(&key) {
+            return Ok(Rc::clone(mesh));
+        }
+
+        let mesh = Rc::new(load_mesh(path, has_texture)?);
+        self.meshes.insert(key, Rc::clone(&mesh));
+        self.watcher.watch(path);
+        Ok(mesh)
+    }
+
+    // The built in Mesh::cube
+    pub fn cube(&mut self) -> Rc<Mesh> {
+        Rc::clone(self.cube.get_or_insert_with(|| Rc::new(Mesh::cube())))
+    }
+
+    pub fn texture(&mut self, path: &Path) -> io::Result<Rc<Image>> {
+        if let Some(texture) = self.textures.get(path) {
+            return Ok(Rc::clone(texture));
+        }
+
+        let texture = Rc::new(load_texture(path)?);
+        self.textures
+            .insert(path.to_path_buf(), Rc::clone(&texture));
+        self.watcher.watch(path);
+        Ok(texture)
+    }
+
+    pub fn material(&mut self, path: &Path) -> io::Result<Rc<Material>> {
+        if let Some(material) = self.materials.get(path) {
+            return Ok(Rc::clone(material));
+        }
+
+        let material = Rc::new(Material::load_from_mtl(&path.to_string_lossy(), self)?);
+        self.materials
+            .insert(path.to_path_buf(), Rc::clone(&material));
+        self.watcher.watch(path);
+        Ok(material)
+    }
+
+    // Loads every file that changed on disk since it was last loaded and swaps the new data into
+    // the objects of `scene` and the `skybox` that were using the old one. A file that fails to
+    // load keeps its old data, and its error is returned alongside the files that did reload
+    pub fn reload_changed(
+        &mut self,
+        scene: &mut Scene,
+        mut skybox: Option<&mut Skybox>,
+    ) -> Vec<(PathBuf, io::Result<()>)> {
+        self.watcher
+            .changed()
+            .into_iter()
+            .map(|path| {
+                let result = self.reload(&path, scene, skybox.as_deref_mut());
+                (path, result)
+            })
+            .collect()
+    }
+
+    fn reload(
+        &mut self,
+        path: &Path,
+        scene: &mut Scene,
+        skybox: Option<&mut Skybox>,
+    ) -> io::Result<()> {
+        // Everything is loaded before anything is swapped -> A broken file changes nothing
+        let mut meshes = Vec::new();
+        for has_texture in [false, true] {
+            let key = (path.to_path_buf(), has_texture);
+            if let Some(old) = self.meshes.get(&key) {
+                meshes.push((key, Rc::clone(old), Rc::new(load_mesh(path, has_texture)?)));
+            }
+        }
+        let texture = match self.textures.get(path) {
+            Some(old) => Some((Rc::clone(old), Rc::new(load_texture(path)?))),
+            None => None,
+        };
+        let material = match self.materials.get(path) {
+            Some(old) => Some((
+                Rc::clone(old),
+                Rc::new(Material::load_from_mtl(&path.to_string_lossy(), self)?),
+            )),
+            None => None,
+        };
+
+        for (key, old, new) in meshes {
+            for (_, object) in scene.iter_mut() {
+                let lod_meshes = object.lod.iter_mut().flat_map(|lod| &mut lod.levels);
+                for mesh in
+                    std::iter::once(&mut object.mesh).chain(lod_meshes.map(|level| &mut level.mesh))
+                {
+                    if Rc::ptr_eq(mesh, &old) {
+                        *mesh = Rc::clone(&new);
+                    }
+                }
+            }
+            self.meshes.insert(key, new);
+        }
+
+        if let Some((old, new)) = texture {
+            // Materials glowing with the texture are copied once each, so objects sharing a
+            // material still share it afterwards
+            let mut replaced: Vec<(Rc<Material>, Rc<Material>)> = Vec::new();
+            let mut replace_material = |material: &mut Rc<Material>| {
+                let uses_texture = material
+                    .emissive_texture
+                    .as_ref()
+                    .is_some_and(|texture| Rc::ptr_eq(texture, &old));
+                if !uses_texture {
+                    return;
+                }
+                let copy = match replaced.iter().find(|(from, _)| Rc::ptr_eq(from, material)) {
+                    Some((_, to)) => Rc::clone(to),
+                    None => {
+                        let mut copy = Material::clone(material);
+                        copy.emissive_texture = Some(Rc::clone(&new));
+                        let copy = Rc::new(copy);
+                        replaced.push((Rc::clone(material), Rc::clone(&copy)));
+                        copy
+                    }
+                };
+                *material = copy;
+            };
+
+            for (_, object) in scene.iter_mut() {
+                if object
+                    .texture
+                    .as_ref()
+                    .is_some_and(|texture| Rc::ptr_eq(texture, &old))
+                {
+                    object.texture = Some(Rc::clone(&new));
+                }
+                replace_material(&mut object.material);
+            }
+            for material in self.materials.values_mut() {
+                replace_material(material);
+            }
+
+            let sky_images = match skybox {
+                Some(Skybox::Equirectangular(image)) => std::slice::from_mut(image),
+                Some(Skybox::Cubemap(faces)) => faces.as_mut_slice(),
+                _ => &mut [],
+            };
+            for image in sky_images {
+                if Rc::ptr_eq(image, &old) {
+                    *image = Rc::clone(&new);
+                }
+            }
+            self.textures.insert(path.to_path_buf(), new);
+        }
+
+        if let Some((old, new)) = material {
+            for (_, object) in scene.iter_mut() {
+                if Rc::ptr_eq(&object.material, &old) {
+                    object.material = Rc::clone(&new);
+                }
+            }
+            self.materials.insert(path.to_path_buf(), new);
+        }
+        Ok(())
+    }
+}
+
+fn load_mesh(path: &Path, has_texture: bool) -> io::Result<Mesh> {
+    let path_str = path.to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    match extension.as_deref() {
+        Some("obj") => Mesh::load_from_obj(&path_str, has_texture),
+        Some("ply") => Mesh::load_from_ply(&path_str),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("expected an .obj or .ply file, got {path_str}"),
+        )),
+    }
+}
@@ -0,0 +1,139 @@
+use macroquad::texture::Image;
+
+// Post-process glow: light given off by emissive surfaces is blurred and added back on top
+pub struct Bloom {
+    // Emission below this in every channel doesn't glow, eg: to keep faint glow maps flat
+    pub threshold: f32,
+    // How strongly the blurred glow is added back onto the frame
+    pub intensity: f32,
+    // Blur radius in pixels of the downscaled glow buffer
+    pub radius: usize,
+    // The glow is computed at 1/downsample of the frame size to keep the blur cheap
+    pub downsample: usize,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Bloom {
+            threshold: 0.0,
+            intensity: 1.0,
+            radius: 4,
+            downsample: 4,
+        }
+    }
+}
+
+impl Bloom {
+    // The emission buffer holds what each pixel emits, as filled in while drawing
+    pub fn apply(&self, image: &mut Image, emission_buffer: &[[f32; 3]]) {
+        let width = image.width();
+        let height = image.height();
+        let downsample = self.downsample.max(1);
+        let glow_width = width.div_ceil(downsample);
+        let glow_height = height.div_ceil(downsample);
+
+        // Bright pass -> Average the emission of each block of pixels, keeping only what is
+        // above the threshold. Saturated colours glow as much as white, and lit or sky pixels
+        // don't glow at all, however bright they are
+        let mut glow = vec![[0.0f32; 3]; glow_width * glow_height];
+        for gy in 0..glow_height {
+            for gx in 0..glow_width {
+                let mut sum = [0.0; 3];
+                let mut count = 0.0;
+                for y in gy * downsample..((gy + 1) * downsample).min(height) {
+                    for x in gx * downsample..((gx + 1) * downsample).min(width) {
+                        let emission = emission_buffer[x + y * width];
+                        for c in 0..3 {
+                            sum[c] += (emission[c] - self.threshold).max(0.0);
+                        }
+                        count += 1.0;
+                    }
+                }
+
+                glow[gx + gy * glow_width] = sum.map(|c| c / count);
+            }
+        }
+
+        // Separable gaussian blur -> Horizontal pass then vertical pass
+        let kernel = gaussian_kernel(self.radius);
+        let glow = blur(&glow, glow_width, glow_height, &kernel, (1, 0));
+        let glow = blur(&glow, glow_width, glow_height, &kernel, (0, 1));
+
+        // Composite -> Add the upscaled glow back onto the frame
+        for y in 0..height {
+            for x in 0..width {
+                let gx = (x as f32 + 0.5) / downsample as f32 - 0.5;
+                let gy = (y as f32 + 0.5) / downsample as f32 - 0.5;
+                let light = sample_bilinear(&glow, glow_width, glow_height, gx, gy);
+
+                let pixel = &mut image.bytes[(x + y * width) * 4..];
+                for c in 0..3 {
+                    let value = pixel[c] as f32 + light[c] * self.intensity * 255.0;
+                    pixel[c] = value.min(255.0) as u8;
+                }
+            }
+        }
+    }
+}
+
+fn gaussian_kernel(radius: usize) -> Vec<f32> {
+    let sigma = (radius as f32 / 2.0).max(0.5);
+    let kernel: Vec<f32> = (0..=radius * 2)
+        .map(|i| {
+            let x = i as f32 - radius as f32;
+            (-(x * x) / (2.0 * sigma * sigma)).exp()
+        })
+        .collect();
+
+    let total: f32 = kernel.iter().sum();
+    kernel.iter().map(|weight| weight / total).collect()
+}
+
+fn blur(
+    source: &[[f32; 3]],
+    width: usize,
+    height: usize,
+    kernel: &[f32],
+    (step_x, step_y): (usize, usize),
+) -> Vec<[f32; 3]> {
+    let radius = (kernel.len() / 2) as isize;
+    let mut result = vec![[0.0f32; 3]; source.len()];
+
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = [0.0; 3];
+            for (i, weight) in kernel.iter().enumerate() {
+                let offset = i as isize - radius;
+                // Clamp to the edge so the borders don't darken
+                let sx = (x as isize + offset * step_x as isize).clamp(0, width as isize - 1);
+                let sy = (y as isize + offset * step_y as isize).clamp(0, height as isize - 1);
+                let sample = source[sx as usize + sy as usize * width];
+                for c in 0..3 {
+                    sum[c] += sample[c] * weight;
+                }
+            }
+            result[x + y * width] = sum;
+        }
+    }
+
+    result
+}
+
+fn sample_bilinear(source: &[[f32; 3]], width: usize, height: usize, x: f32, y: f32) -> [f32; 3] {
+    let x = x.clamp(0.0, (width - 1) as f32);
+    let y = y.clamp(0.0, (height - 1) as f32);
+    let x0 = x as usize;
+    let y0 = y as usize;
+    let x1 = (x0 + 1).min(width - 1);
+    let y1 = (y0 + 1).min(height - 1);
+    let tx = x - x0 as f32;
+    let ty = y - y0 as f32;
+
+    let mut result = [0.0; 3];
+    for (c, value) in result.iter_mut().enumerate() {
+        let top = source[x0 + y0 * width][c] * (1.0 - tx) + source[x1 + y0 * width][c] * tx;
+        let bottom = source[x0 + y1 * width][c] * (1.0 - tx) + source[x1 + y1 * width][c] * tx;
+        *value = top * (1.0 - ty) + bottom * ty;
+    }
+    result
+}
@@ -0,0 +1,112 @@
+use crate::Vector3;
+
+// Most items in a leaf before it gets split in two
+const LEAF_SIZE: usize = 4;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Node {
+    min: Vector3,
+    max: Vector3,
+    // Leaves hold items[first..first + count], other nodes have children at first and first + 1
+    first: usize,
+    count: usize,
+}
+
+// Bounding volume hierarchy -> A tree of boxes that lets whole groups of items be skipped at once
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Bvh {
+    nodes: Vec<Node>,
+    items: Vec<usize>,
+}
+
+impl Bvh {
+    // Items are given by their bounding boxes and referred to by their index in the slice
+    pub(crate) fn new(bounds: &[(Vector3, Vector3)]) -> Self {
+        let mut bvh = Bvh {
+            nodes: Vec::new(),
+            items: (0..bounds.len()).collect(),
+        };
+        if !bounds.is_empty() {
+            bvh.nodes.push(Node::default());
+            bvh.build(0, 0, bounds.len(), bounds);
+        }
+        bvh
+    }
+
+    fn build(&mut self, node: usize, first: usize, count: usize, bounds: &[(Vector3, Vector3)]) {
+        let items = &mut self.items[first..first + count];
+
+        let mut min = Vector3::new(f32::MAX, f32::MAX, f32::MAX);
+        let mut max = Vector3::new(f32::MIN, f32::MIN, f32::MIN);
+        for &item in items.iter() {
+            let (item_min, item_max) = &bounds[item];
+            min = Vector3::new(
+                min.x.min(item_min.x),
+                min.y.min(item_min.y),
+                min.z.min(item_min.z),
+            );
+            max = Vector3::new(
+                max.x.max(item_max.x),
+                max.y.max(item_max.y),
+                max.z.max(item_max.z),
+            );
+        }
+
+        self.nodes[node] = Node {
+            min,
+            max,
+            first,
+            count,
+        };
+        if count <= LEAF_SIZE {
+            return;
+        }
+
+        // Split in half at the middle item along the longest side
+        let size = [max.x - min.x, max.y - min.y, max.z - min.z];
+        let axis = (0..3).max_by(|&a, &b| size[a].total_cmp(&size[b])).unwrap();
+        let center = |item: usize| {
+            let (item_min, item_max) = &bounds[item];
+            match axis {
+                0 => item_min.x + item_max.x,
+                1 => item_min.y + item_max.y,
+                _ => item_min.z + item_max.z,
+            }
+        };
+        let half = count / 2;
+        items.select_nth_unstable_by(half, |&a, &b| center(a).total_cmp(&center(b)));
+
+        let left = self.nodes.len();
+        self.nodes.push(Node::default());
+        self.nodes.push(Node::default());
+        self.nodes[node].first = left;
+        self.nodes[node].count = 0;
+
+        self.build(left, first, half, bounds);
+        self.build(left + 1, first + half, count - half, bounds);
+    }
+
+    // Every item in a leaf whose box passes the test, along with all the boxes above it
+    pub(crate) fn query(&self, mut overlaps: impl FnMut(&Vector3, &Vector3) -> bool) -> Vec<usize> {
+        let mut found = Vec::new();
+        if self.nodes.is_empty() {
+            return found;
+        }
+
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !overlaps(&node.min, &node.max) {
+                continue;
+            }
+
+            if node.count > 0 {
+                found.extend_from_slice(&self.items[node.first..node.first + node.count]);
+            } else {
+                stack.push(node.first);
+                stack.push(node.first + 1);
+            }
+        }
+        found
+    }
+}
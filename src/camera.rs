@@ -1,104 +1,534 @@
+use std::f32::consts::FRAC_PI_2;
+
 use macroquad::{
-    input::{KeyCode, is_key_down},
+    input::{mouse_delta_position, mouse_wheel, set_cursor_grab, show_mouse},
     time::get_frame_time,
 };
 
-use crate::{Vector3, matrix::*};
-const CAMERA_SPEED: f32 = 15.0;
+use crate::{
+    FAR, FOV, NEAR, Vector3,
+    collision::Collider,
+    input::{Action, InputMap},
+    matrix::*,
+};
+// Just short of straight up/down, so the view never flips over
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+// Closest the orbit camera gets to its target
+const NEAR_DISTANCE: f32 = 0.5;
+// Fraction of the field of view zoomed per scroll step
+const ZOOM_SPEED: f32 = 0.1;
+const MIN_FOV: f32 = 10.0;
+const MAX_FOV: f32 = 120.0;
+const MIN_ORTHO_HEIGHT: f32 = 0.1;
 
 pub struct Camera {
     pub position: Vector3,
-    pub rotation_x: f32,
-    pub rotation_y: f32,
-    pub rotation_z: f32,
-    pub up: Vector3,
+    // Turns the camera's local axes into world space
+    pub orientation: Quaternion,
+    // Look around with the mouse instead of the arrow keys
+    pub mouse_look: bool,
+    // Turn and pitch around the camera's own axes like an aircraft.
+    // Otherwise turning is around the world's up axis and pitch stops short of straight up/down
+    pub flight_controls: bool,
+    // Vertical field of view in degrees -> Scrolling zooms by narrowing it
+    pub fov: f32,
+    pub near: f32,
+    pub far: f32,
+    // Parallel projection for technical or isometric views
+    pub orthographic: bool,
+    // World units visible from the bottom to the top of the screen when orthographic
+    pub ortho_height: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Camera {
     pub fn new() -> Self {
         Camera {
             position: Vector3::new(0.0, 0.0, 0.0),
-            rotation_x: 0.0,
-            rotation_y: 0.0,
-            rotation_z: 0.0,
-            up: Vector3::new(0.0, 1.0, 0.0),
+            orientation: Quaternion::identity(),
+            mouse_look: false,
+            flight_controls: false,
+            fov: FOV,
+            near: NEAR,
+            far: FAR,
+            orthographic: false,
+            ortho_height: 20.0,
+        }
+    }
+
+    // Positive yaw turns right, positive pitch looks up and roll is applied before either
+    pub fn set_rotation(&mut self, yaw: f32, pitch: f32, roll: f32) {
+        let yaw = Quaternion::from_axis_angle(&Vector3::up(), yaw);
+        let pitch = Quaternion::from_axis_angle(&Vector3::right(), pitch);
+        let roll = Quaternion::from_axis_angle(&Vector3::forward(), roll);
+        self.orientation = quat_multiply(&yaw, &quat_multiply(&pitch, &roll));
+    }
+
+    pub fn rotate_yaw(&mut self, angle: f32) {
+        if self.flight_controls {
+            self.rotate_local(&Vector3::up(), angle);
+        } else {
+            let yaw = Quaternion::from_axis_angle(&Vector3::up(), angle);
+            self.orientation = quat_multiply(&yaw, &self.orientation).normalize();
+        }
+    }
+
+    pub fn rotate_pitch(&mut self, mut angle: f32) {
+        if !self.flight_controls {
+            let pitch = self.direction().y.clamp(-1.0, 1.0).asin();
+            angle = (pitch + angle).clamp(-MAX_PITCH, MAX_PITCH) - pitch;
         }
+        self.rotate_local(&Vector3::right(), angle);
+    }
+
+    pub fn rotate_roll(&mut self, angle: f32) {
+        self.rotate_local(&Vector3::forward(), angle);
+    }
+
+    // Rotates around one of the camera's own axes
+    fn rotate_local(&mut self, axis: &Vector3, angle: f32) {
+        let rotation = Quaternion::from_axis_angle(axis, angle);
+        self.orientation = quat_multiply(&self.orientation, &rotation).normalize();
+    }
+
+    // Grabs and hides the cursor while mouse-look is on
+    pub fn set_mouse_look(&mut self, enabled: bool) {
+        self.mouse_look = enabled;
+        set_cursor_grab(enabled);
+        show_mouse(!enabled);
     }
 
     pub fn direction(&self) -> Vector3 {
-        let front = Vector3::new(0.0, 0.0, 1.0);
-        let rotation_y = rotate_y(self.rotation_y);
-        let rotation_x = rotate_x(self.rotation_x);
-        let rotation_mat = mat_multiply(&rotation_x, &rotation_y);
-        mult_vec_mat(&front, &rotation_mat)
+        quat_rotate(&self.orientation, &Vector3::forward())
     }
 
     pub fn right(&self) -> Vector3 {
-        let right = Vector3::new(1.0, 0.0, 0.0);
-        let rotation_y = rotate_y(self.rotation_y);
-        let rotation_x = rotate_x(self.rotation_x);
-        let rotation_mat = mat_multiply(&rotation_x, &rotation_y);
-        mult_vec_mat(&right, &rotation_mat)
+        quat_rotate(&self.orientation, &Vector3::right())
     }
 
     pub fn up(&self) -> Vector3 {
-        let up = Vector3::new(0.0, 1.0, 0.0);
-        let rotation_y = rotate_y(self.rotation_y);
-        let rotation_x = rotate_x(self.rotation_x);
-        let rotation_mat = mat_multiply(&rotation_x, &rotation_y);
-        mult_vec_mat(&up, &rotation_mat)
+        quat_rotate(&self.orientation, &Vector3::up())
     }
 
     pub fn return_view_mat(&self) -> [[f32; 4]; 4] {
         let target = vec_add(&self.position, &self.direction());
-        let point_at = point_at_mat(&self.position, &target, &self.up);
-        let look_at = quick_inverse_mat(&point_at);
-        look_at
+        let point_at = point_at_mat(&self.position, &target, &self.up());
+        quick_inverse_mat(&point_at)
     }
 
-    pub fn handle_user_input(&mut self) {
-        let delta = get_frame_time();
+    pub fn projection_matrix(&self, aspect_ratio: f32) -> Mat4x4 {
+        if self.orthographic {
+            orthographic_matrix(
+                self.ortho_height * aspect_ratio,
+                self.ortho_height,
+                self.near,
+                self.far,
+            )
+        } else {
+            projection_matrix(aspect_ratio, self.fov, self.near, self.far)
+        }
+    }
+
+    // Scroll steps in -> Narrower field of view, or a smaller area when orthographic
+    pub fn zoom(&mut self, steps: f32) {
+        let scale = (1.0 - ZOOM_SPEED).powf(steps);
+        if self.orthographic {
+            self.ortho_height = (self.ortho_height * scale).max(MIN_ORTHO_HEIGHT);
+        } else {
+            self.fov = (self.fov * scale).clamp(MIN_FOV, MAX_FOV);
+        }
+    }
+
+    // Turning, zooming and mouse-look -> Everything apart from moving around
+    pub fn handle_look_input(&mut self, input: &InputMap) {
+        let turn_speed = input.turn_speed * get_frame_time();
 
-        let forward = vec_mul(&self.direction(), CAMERA_SPEED * delta);
         // Rotation of camera
-        if is_key_down(KeyCode::Up) {
-            self.rotation_x += CAMERA_SPEED / 10.0 * delta;
+        if input.is_down(Action::LookUp) {
+            self.rotate_pitch(turn_speed);
+        }
+        if input.is_down(Action::LookDown) {
+            self.rotate_pitch(-turn_speed);
         }
-        if is_key_down(KeyCode::Down) {
-            self.rotation_x -= CAMERA_SPEED / 10.0 * delta;
+        if input.is_down(Action::LookLeft) {
+            self.rotate_yaw(-turn_speed);
         }
-        if is_key_down(KeyCode::Left) {
-            self.rotation_y -= CAMERA_SPEED / 10.0 * delta;
+        if input.is_down(Action::LookRight) {
+            self.rotate_yaw(turn_speed);
         }
-        if is_key_down(KeyCode::Right) {
-            self.rotation_y += CAMERA_SPEED / 10.0 * delta;
+        if input.is_down(Action::RollLeft) {
+            self.rotate_roll(-turn_speed);
+        }
+        if input.is_down(Action::RollRight) {
+            self.rotate_roll(turn_speed);
+        }
+
+        if input.is_pressed(Action::ToggleFlightControls) {
+            self.flight_controls = !self.flight_controls;
+            // Level out when going back to walking around
+            if !self.flight_controls {
+                let direction = self.direction();
+                let yaw = (-direction.x).atan2(direction.z);
+                let pitch = direction.y.clamp(-1.0, 1.0).asin();
+                self.set_rotation(yaw, pitch.clamp(-MAX_PITCH, MAX_PITCH), 0.0);
+            }
         }
 
+        let (_, scroll) = mouse_wheel();
+        if scroll != 0.0 {
+            self.zoom(scroll.signum());
+        }
+
+        if input.is_pressed(Action::ToggleMouseLook) {
+            self.set_mouse_look(!self.mouse_look);
+        }
+        if input.is_pressed(Action::ReleaseMouse) && self.mouse_look {
+            self.set_mouse_look(false);
+        }
+        if self.mouse_look {
+            // Delta is the previous minus the current position, in screen units of -1.0 to 1.0
+            let mouse_delta = mouse_delta_position();
+            let pitch_delta = if input.invert_y {
+                -mouse_delta.y
+            } else {
+                mouse_delta.y
+            };
+            self.rotate_yaw(-mouse_delta.x * input.mouse_sensitivity);
+            self.rotate_pitch(pitch_delta * input.mouse_sensitivity);
+        }
+    }
+
+    pub fn handle_user_input(&mut self, input: &InputMap) {
+        self.handle_look_input(input);
+
+        let move_speed = input.move_speed * get_frame_time();
+        let forward = vec_mul(&self.direction(), move_speed);
+
         // Movement of camera
-        if is_key_down(KeyCode::W) {
+        if input.is_down(Action::MoveForward) {
             self.position = vec_add(&self.position, &forward);
         }
-        if is_key_down(KeyCode::S) {
+        if input.is_down(Action::MoveBackward) {
             self.position = vec_sub(&self.position, &forward);
         }
-        if is_key_down(KeyCode::A) {
+        if input.is_down(Action::MoveLeft) {
             // Move left relative to camera's direction
-            self.position = vec_add(
-                &self.position,
-                &vec_mul(&self.right(), CAMERA_SPEED * delta),
-            );
+            self.position = vec_add(&self.position, &vec_mul(&self.right(), move_speed));
+        }
+        if input.is_down(Action::MoveRight) {
+            self.position = vec_add(&self.position, &vec_mul(&self.right(), -move_speed));
+        }
+        if input.is_down(Action::MoveUp) {
+            self.position = vec_add(&self.position, &vec_mul(&self.up(), move_speed));
+        }
+        if input.is_down(Action::MoveDown) {
+            self.position = vec_add(&self.position, &vec_mul(&self.up(), -move_speed));
+        }
+    }
+}
+
+// Rotates around a target point -> For inspecting a single model
+pub struct OrbitCamera {
+    pub target: Vector3,
+    pub distance: f32,
+    // Same conventions as Camera::set_rotation
+    pub yaw: f32,
+    pub pitch: f32,
+    // Radians turned when the mouse is dragged across half of the screen
+    pub rotate_sensitivity: f32,
+    // Fraction of the distance zoomed per scroll step
+    pub zoom_speed: f32,
+}
+
+impl Default for OrbitCamera {
+    fn default() -> Self {
+        Self::new(Vector3::new(0.0, 0.0, 0.0), 10.0)
+    }
+}
+
+impl OrbitCamera {
+    pub fn new(target: Vector3, distance: f32) -> Self {
+        OrbitCamera {
+            target,
+            distance,
+            yaw: 0.0,
+            pitch: 0.0,
+            rotate_sensitivity: 2.0,
+            zoom_speed: 0.1,
         }
-        if is_key_down(KeyCode::D) {
-            self.position = vec_add(
-                &self.position,
-                &vec_mul(&self.right(), -CAMERA_SPEED * delta),
+    }
+
+    // Centers on a bounding box and backs off until all of it fits in the field of view
+    pub fn frame(&mut self, min: &Vector3, max: &Vector3, fov: f32) {
+        self.target = vec_div(&vec_add(min, max), 2.0);
+
+        let half_diagonal = vec_sub(max, &self.target);
+        let radius = dot_product(&half_diagonal, &half_diagonal).sqrt();
+        let half_fov = fov.to_radians() / 2.0;
+        self.distance = (radius / half_fov.sin()).max(NEAR_DISTANCE);
+    }
+
+    // From the camera towards the target
+    pub fn direction(&self) -> Vector3 {
+        let front = Vector3::new(0.0, 0.0, 1.0);
+        let rotation_mat = mat_multiply(&rotate_x(self.pitch), &rotate_y(self.yaw));
+        mult_vec_mat(&front, &rotation_mat)
+    }
+
+    pub fn position(&self) -> Vector3 {
+        vec_sub(&self.target, &vec_mul(&self.direction(), self.distance))
+    }
+
+    pub fn return_view_mat(&self) -> [[f32; 4]; 4] {
+        let point_at = point_at_mat(&self.position(), &self.target, &Vector3::up());
+        quick_inverse_mat(&point_at)
+    }
+
+    // Moves a camera to this orbit's viewpoint, so it gives the same view matrix
+    pub fn apply_to(&self, camera: &mut Camera) {
+        camera.position = self.position();
+        camera.set_rotation(self.yaw, self.pitch, 0.0);
+    }
+
+    // Left drag rotates, middle drag pans and scrolling zooms
+    pub fn handle_user_input(&mut self, input: &InputMap) {
+        // Delta is the previous minus the current position, in screen units of -1.0 to 1.0
+        let mouse_delta = mouse_delta_position();
+
+        if input.is_down(Action::OrbitRotate) {
+            self.yaw -= mouse_delta.x * self.rotate_sensitivity;
+            self.pitch += mouse_delta.y * self.rotate_sensitivity;
+            self.pitch = self.pitch.clamp(-MAX_PITCH, MAX_PITCH);
+        }
+
+        if input.is_down(Action::OrbitPan) {
+            // Move the target with the cursor, further when zoomed out
+            let rotation_mat = mat_multiply(&rotate_x(self.pitch), &rotate_y(self.yaw));
+            // Points to the left of the screen, like Camera::right
+            let left = mult_vec_mat(&Vector3::right(), &rotation_mat);
+            let up = mult_vec_mat(&Vector3::up(), &rotation_mat);
+
+            let pan = vec_add(
+                &vec_mul(&left, -mouse_delta.x * self.distance),
+                &vec_mul(&up, -mouse_delta.y * self.distance),
             );
+            self.target = vec_add(&self.target, &pan);
+        }
+
+        let (_, scroll) = mouse_wheel();
+        if scroll != 0.0 {
+            self.distance *= 1.0 - self.zoom_speed * scroll.signum();
+            self.distance = self.distance.max(NEAR_DISTANCE);
+        }
+    }
+}
+
+// What the capsule touched while being pushed out of the level
+#[derive(Default)]
+struct Contacts {
+    ground: bool,
+    ceiling: bool,
+}
+
+// First person walking -> A capsule that falls under gravity and collides with the level
+pub struct WalkController {
+    // Bottom of the capsule
+    pub feet: Vector3,
+    pub radius: f32,
+    pub height: f32,
+    // Camera height above the feet
+    pub eye_height: f32,
+    // Tallest ledge that can be walked up without jumping
+    pub step_height: f32,
+    pub walk_speed: f32,
+    pub gravity: f32,
+    pub jump_speed: f32,
+    vertical_speed: f32,
+    grounded: bool,
+}
+
+impl Default for WalkController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WalkController {
+    pub fn new() -> Self {
+        WalkController {
+            feet: Vector3::new(0.0, 0.0, 0.0),
+            radius: 0.5,
+            height: 1.8,
+            eye_height: 1.6,
+            step_height: 0.5,
+            walk_speed: 5.0,
+            gravity: 20.0,
+            jump_speed: 7.0,
+            vertical_speed: 0.0,
+            grounded: false,
+        }
+    }
+
+    // Drops the capsule in below the camera
+    pub fn place_at(&mut self, camera: &Camera) {
+        self.feet = vec_sub(&camera.position, &Vector3::new(0.0, self.eye_height, 0.0));
+        self.vertical_speed = 0.0;
+        self.grounded = false;
+    }
+
+    pub fn is_grounded(&self) -> bool {
+        self.grounded
+    }
+
+    // Walks along the ground in the direction the camera faces and moves the camera along
+    pub fn update(&mut self, camera: &mut Camera, input: &InputMap, collider: &Collider) {
+        // Only the heading matters for walking, not where the camera looks up or down
+        let flatten = |v: Vector3| {
+            let flat = Vector3::new(v.x, 0.0, v.z);
+            if dot_product(&flat, &flat) > 1e-6 {
+                flat.normalize()
+            } else {
+                flat
+            }
+        };
+        let forward = flatten(camera.direction());
+        // Points to the left of the screen, like Camera::right
+        let left = flatten(camera.right());
+
+        let mut walk = Vector3::new(0.0, 0.0, 0.0);
+        if input.is_down(Action::MoveForward) {
+            walk = vec_add(&walk, &forward);
+        }
+        if input.is_down(Action::MoveBackward) {
+            walk = vec_sub(&walk, &forward);
+        }
+        if input.is_down(Action::MoveLeft) {
+            walk = vec_add(&walk, &left);
+        }
+        if input.is_down(Action::MoveRight) {
+            walk = vec_sub(&walk, &left);
+        }
+
+        let jump = input.is_pressed(Action::Jump);
+        self.move_by(&walk, jump, collider, get_frame_time());
+
+        camera.position = vec_add(&self.feet, &Vector3::new(0.0, self.eye_height, 0.0));
+    }
+
+    // Advances by `delta` seconds, walking towards `direction` (flat, any length) at walk_speed
+    pub fn move_by(&mut self, direction: &Vector3, jump: bool, collider: &Collider, delta: f32) {
+        if dot_product(direction, direction) > 1e-6 {
+            let walk = vec_mul(&direction.normalize(), self.walk_speed * delta);
+            let mut feet = self.feet;
+            self.sweep(&mut feet, &walk, collider);
+            self.feet = feet;
+        }
+
+        if self.grounded && jump {
+            self.vertical_speed = self.jump_speed;
+            self.grounded = false;
         }
-        if is_key_down(KeyCode::Space) {
-            self.position = vec_add(&self.position, &vec_mul(&self.up(), CAMERA_SPEED * delta));
+
+        if self.grounded {
+            // Follow the ground down slopes and steps instead of floating off them
+            let mut feet = self.feet;
+            let step_down = Vector3::new(0.0, -self.step_height, 0.0);
+            if self.sweep(&mut feet, &step_down, collider).ground {
+                self.feet = feet;
+            } else {
+                self.grounded = false;
+            }
+        } else {
+            self.vertical_speed -= self.gravity * delta;
+            let fall = Vector3::new(0.0, self.vertical_speed * delta, 0.0);
+            let mut feet = self.feet;
+            let contacts = self.sweep(&mut feet, &fall, collider);
+            self.feet = feet;
+
+            if contacts.ground && self.vertical_speed <= 0.0 {
+                self.grounded = true;
+                self.vertical_speed = 0.0;
+            }
+            if contacts.ceiling && self.vertical_speed > 0.0 {
+                self.vertical_speed = 0.0;
+            }
+        }
+    }
+
+    // Moves in steps short enough not to pass through thin walls, resolving collisions after each
+    fn sweep(&self, feet: &mut Vector3, offset: &Vector3, collider: &Collider) -> Contacts {
+        let distance = dot_product(offset, offset).sqrt();
+        let steps = (distance / (self.radius * 0.5)).ceil().max(1.0);
+        let step = vec_mul(offset, 1.0 / steps);
+
+        let mut contacts = Contacts::default();
+        for _ in 0..steps as usize {
+            *feet = vec_add(feet, &step);
+            let resolved = self.resolve(feet, collider);
+            contacts.ground |= resolved.ground;
+            contacts.ceiling |= resolved.ceiling;
         }
-        if is_key_down(KeyCode::LeftShift) {
-            self.position = vec_add(&self.position, &vec_mul(&self.up(), -CAMERA_SPEED * delta));
+        contacts
+    }
+
+    // Pushes the capsule out of the level, sliding along walls and stepping up onto ledges.
+    // The capsule is covered by a column of spheres
+    fn resolve(&self, feet: &mut Vector3, collider: &Collider) -> Contacts {
+        // Steeper than this is a wall rather than ground (about 45 degrees)
+        const MIN_GROUND_NORMAL_Y: f32 = 0.7;
+
+        let spheres = (self.height / self.radius).ceil().max(2.0) as usize;
+        let spacing = (self.height - 2.0 * self.radius) / (spheres - 1) as f32;
+
+        let mut contacts = Contacts::default();
+        for _ in 0..4 {
+            let mut pushed = false;
+            for i in 0..spheres {
+                let offset = self.radius + spacing * i as f32;
+                let mut center = vec_add(feet, &Vector3::new(0.0, offset, 0.0));
+
+                for point in collider.sphere_contacts(&center, self.radius) {
+                    let offset = vec_sub(&center, &point);
+                    let distance = dot_product(&offset, &offset).sqrt();
+                    // An earlier contact may already have pushed the sphere clear of this one
+                    if distance >= self.radius {
+                        continue;
+                    }
+                    pushed = true;
+
+                    // Low ledge under the bottom sphere -> Lift onto it instead of pushing back
+                    if i == 0 && point.y < center.y - 1e-3 && point.y <= feet.y + self.step_height {
+                        let horizontal = offset.x * offset.x + offset.z * offset.z;
+                        center.y = point.y + (self.radius * self.radius - horizontal).sqrt();
+                        contacts.ground = true;
+                        continue;
+                    }
+
+                    let normal = if distance > 1e-6 {
+                        vec_mul(&offset, 1.0 / distance)
+                    } else {
+                        Vector3::up()
+                    };
+                    center = vec_add(&center, &vec_mul(&normal, self.radius - distance));
+
+                    if normal.y >= MIN_GROUND_NORMAL_Y {
+                        contacts.ground = true;
+                    } else if normal.y <= -MIN_GROUND_NORMAL_Y {
+                        contacts.ceiling = true;
+                    }
+                }
+                *feet = vec_sub(&center, &Vector3::new(0.0, offset, 0.0));
+            }
+
+            if !pushed {
+                break;
+            }
         }
+        contacts
     }
 }
@@ -0,0 +1,232 @@
+use crate::{
+    Mesh, Scene, Vector3,
+    bvh::Bvh,
+    matrix::{Mat4x4, cross_product, dot_product, mult_vec_mat, vec_add, vec_mul, vec_sub},
+};
+
+// World space triangles that can be walked on and bumped into
+#[derive(Default)]
+pub struct Collider {
+    triangles: Vec<[Vector3; 3]>,
+    // Bounding box of each triangle -> Skips most of them without any real work
+    bounds: Vec<(Vector3, Vector3)>,
+    // Built over the boxes above -> Only the triangles near a query get looked at
+    bvh: Bvh,
+}
+
+impl Collider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Every object in the scene, where it is right now
+    pub fn from_scene(scene: &Scene) -> Self {
+        let mut collider = Collider::new();
+        for (_, object, transform_mat) in scene.iter() {
+            collider.add_mesh(&object.mesh, transform_mat);
+        }
+        collider.build();
+        collider
+    }
+
+    // Places the mesh the same way Mesh::draw does. Queries don't see it until the next build
+    pub fn add_mesh(&mut self, mesh: &Mesh, transform_mat: &Mat4x4) {
+        for face in &mesh.faces {
+            let triangle = face
+                .vertices
+                .map(|v| mult_vec_mat(&mesh.vertices[v], transform_mat));
+
+            let mut min = triangle[0];
+            let mut max = triangle[0];
+            for vertex in &triangle[1..] {
+                min = Vector3::new(
+                    min.x.min(vertex.x),
+                    min.y.min(vertex.y),
+                    min.z.min(vertex.z),
+                );
+                max = Vector3::new(
+                    max.x.max(vertex.x),
+                    max.y.max(vertex.y),
+                    max.z.max(vertex.z),
+                );
+            }
+
+            self.triangles.push(triangle);
+            self.bounds.push((min, max));
+        }
+    }
+
+    // Rebuilds the BVH over every triangle added so far -> Once after the last add_mesh, not
+    // after each one
+    pub fn build(&mut self) {
+        self.bvh = Bvh::new(&self.bounds);
+    }
+
+    // Closest point on each triangle that overlaps the sphere
+    pub fn sphere_contacts(&self, center: &Vector3, radius: f32) -> Vec<Vector3> {
+        let mut contacts = Vec::new();
+
+        let overlaps = |min: &Vector3, max: &Vector3| {
+            center.x + radius >= min.x
+                && center.x - radius <= max.x
+                && center.y + radius >= min.y
+                && center.y - radius <= max.y
+                && center.z + radius >= min.z
+                && center.z - radius <= max.z
+        };
+
+        for index in self.bvh.query(overlaps) {
+            let triangle = &self.triangles[index];
+            let closest = closest_point_on_triangle(center, triangle);
+            let offset = vec_sub(center, &closest);
+            if dot_product(&offset, &offset) < radius * radius {
+                contacts.push(closest);
+            }
+        }
+
+        contacts
+    }
+
+    // Distance along the ray to the first triangle it hits, in units of `direction`
+    pub fn intersect_ray(&self, origin: &Vector3, direction: &Vector3) -> Option<f32> {
+        ray_bvh_intersection(&self.bvh, origin, direction, |index| {
+            ray_triangle_intersection(origin, direction, &self.triangles[index])
+        })
+        .map(|(t, _)| t)
+    }
+}
+
+// Closest hit among the items whose boxes the ray passes through, with the item it hit
+pub(crate) fn ray_bvh_intersection(
+    bvh: &Bvh,
+    origin: &Vector3,
+    direction: &Vector3,
+    mut intersect: impl FnMut(usize) -> Option<f32>,
+) -> Option<(f32, usize)> {
+    bvh.query(|min, max| ray_box_intersection(origin, direction, min, max))
+        .into_iter()
+        .filter_map(|index| intersect(index).map(|t| (t, index)))
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+}
+
+// Slab test -> Does the ray pass through the box anywhere in front of its origin
+fn ray_box_intersection(
+    origin: &Vector3,
+    direction: &Vector3,
+    min: &Vector3,
+    max: &Vector3,
+) -> bool {
+    let mut t_min = 0.0_f32;
+    let mut t_max = f32::MAX;
+
+    for (origin, direction, min, max) in [
+        (origin.x, direction.x, min.x, max.x),
+        (origin.y, direction.y, min.y, max.y),
+        (origin.z, direction.z, min.z, max.z),
+    ] {
+        if direction == 0.0 {
+            // Parallel to the slab -> Has to start between its sides
+            if origin < min || origin > max {
+                return false;
+            }
+            continue;
+        }
+
+        let t1 = (min - origin) / direction;
+        let t2 = (max - origin) / direction;
+        t_min = t_min.max(t1.min(t2));
+        t_max = t_max.min(t1.max(t2));
+        if t_min > t_max {
+            return false;
+        }
+    }
+
+    true
+}
+
+// Möller–Trumbore -> Hits both sides of the triangle, only in front of the origin
+pub(crate) fn ray_triangle_intersection(
+    origin: &Vector3,
+    direction: &Vector3,
+    [a, b, c]: &[Vector3; 3],
+) -> Option<f32> {
+    let ab = vec_sub(b, a);
+    let ac = vec_sub(c, a);
+
+    let p = cross_product(direction, &ac);
+    let determinant = dot_product(&ab, &p);
+    if determinant.abs() < f32::EPSILON {
+        return None;
+    }
+    let inverse = 1.0 / determinant;
+
+    let ao = vec_sub(origin, a);
+    let u = dot_product(&ao, &p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q = cross_product(&ao, &ab);
+    let v = dot_product(direction, &q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t = dot_product(&ac, &q) * inverse;
+    (t >= 0.0).then_some(t)
+}
+
+// Closest point to p that lies on the triangle (Real-Time Collision Detection, 5.1.5)
+pub(crate) fn closest_point_on_triangle(p: &Vector3, [a, b, c]: &[Vector3; 3]) -> Vector3 {
+    let ab = vec_sub(b, a);
+    let ac = vec_sub(c, a);
+
+    // Vertex region of a
+    let ap = vec_sub(p, a);
+    let d1 = dot_product(&ab, &ap);
+    let d2 = dot_product(&ac, &ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return *a;
+    }
+
+    // Vertex region of b
+    let bp = vec_sub(p, b);
+    let d3 = dot_product(&ab, &bp);
+    let d4 = dot_product(&ac, &bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return *b;
+    }
+
+    // Edge region of ab
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return vec_add(a, &vec_mul(&ab, d1 / (d1 - d3)));
+    }
+
+    // Vertex region of c
+    let cp = vec_sub(p, c);
+    let d5 = dot_product(&ab, &cp);
+    let d6 = dot_product(&ac, &cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return *c;
+    }
+
+    // Edge region of ac
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return vec_add(a, &vec_mul(&ac, d2 / (d2 - d6)));
+    }
+
+    // Edge region of bc
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && d4 - d3 >= 0.0 && d5 - d6 >= 0.0 {
+        let t = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+        return vec_add(b, &vec_mul(&vec_sub(c, b), t));
+    }
+
+    // Inside the face
+    let denominator = 1.0 / (va + vb + vc);
+    let v = vb * denominator;
+    let w = vc * denominator;
+    vec_add(a, &vec_add(&vec_mul(&ab, v), &vec_mul(&ac, w)))
+}
@@ -0,0 +1,64 @@
+use macroquad::color::Color;
+
+pub enum FogMode {
+    // Fades in linearly from nothing at `start` to fully fogged at `end` (view space depth)
+    Linear {
+        start: f32,
+        end: f32,
+    },
+    // Thickens exponentially with depth
+    Exponential {
+        density: f32,
+    },
+    // Exponential fog that is densest at `base_height` and thins out above it by `falloff`
+    Height {
+        density: f32,
+        falloff: f32,
+        base_height: f32,
+    },
+}
+
+pub struct Fog {
+    pub mode: FogMode,
+    // Should match the clear colour so fully fogged geometry disappears into the background
+    pub color: Color,
+}
+
+impl Fog {
+    pub fn new(mode: FogMode, color: Color) -> Self {
+        Fog { mode, color }
+    }
+
+    // How much of a surface is hidden by fog, from 0.0 (clear) to 1.0 (fully fogged)
+    pub fn amount(&self, depth: f32, camera_height: f32, point_height: f32) -> f32 {
+        let amount = match self.mode {
+            // No range to fade over -> Everything past `end` is fully fogged
+            FogMode::Linear { start, end } if end <= start => f32::from(depth >= end),
+            FogMode::Linear { start, end } => (depth - start) / (end - start),
+            FogMode::Exponential { density } => 1.0 - (-density * depth).exp(),
+            FogMode::Height {
+                density,
+                falloff,
+                base_height,
+            } => {
+                // Integrate the density exp(-falloff * (height - base_height)) along the view ray
+                let rise = falloff * (point_height - camera_height);
+                let ray_factor = if rise.abs() > 0.0001 {
+                    (1.0 - (-rise).exp()) / rise
+                } else {
+                    1.0
+                };
+                let camera_density = density * (-falloff * (camera_height - base_height)).exp();
+
+                1.0 - (-camera_density * ray_factor * depth).exp()
+            }
+        };
+
+        amount.clamp(0.0, 1.0)
+    }
+
+    pub fn apply(&self, color: Color, depth: f32, camera_height: f32, point_height: f32) -> Color {
+        let amount = self.amount(depth, camera_height, point_height);
+        Color::from_vec(color.to_vec().lerp(self.color.to_vec(), amount))
+    }
+}
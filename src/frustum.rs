@@ -0,0 +1,76 @@
+use crate::{
+    Vector3,
+    matrix::{Mat4x4, dot_product, is_orthographic, mat_multiply},
+};
+
+// The part of the world the camera can see, as planes facing inwards: a point p is inside a
+// plane (a, b, c, d) when a * p.x + b * p.y + c * p.z + d >= 0
+pub struct Frustum {
+    // Near, left, right, bottom and top. Nothing is clipped at the far plane, so it isn't here
+    planes: [[f32; 4]; 5],
+}
+
+impl Frustum {
+    // Planes in world space, taken from the columns of the view and projection matrices
+    pub fn new(view_mat: &Mat4x4, projection_mat: &Mat4x4, near: f32) -> Self {
+        let column = |mat: &Mat4x4, j: usize| [mat[0][j], mat[1][j], mat[2][j], mat[3][j]];
+        let combine =
+            |a: [f32; 4], b: [f32; 4], sign: f32| [0, 1, 2, 3].map(|i| a[i] + sign * b[i]);
+
+        // Points in front of the camera end up with a negative w under a perspective projection
+        let clip_mat = mat_multiply(view_mat, projection_mat);
+        let w = column(&clip_mat, 3).map(|value| {
+            if is_orthographic(projection_mat) {
+                value
+            } else {
+                -value
+            }
+        });
+        let x = column(&clip_mat, 0);
+        let y = column(&clip_mat, 1);
+
+        // View space depth >= near
+        let mut near_plane = column(view_mat, 2);
+        near_plane[3] -= near;
+
+        Frustum {
+            planes: [
+                near_plane,
+                combine(w, x, 1.0),
+                combine(w, x, -1.0),
+                combine(w, y, 1.0),
+                combine(w, y, -1.0),
+            ],
+        }
+    }
+
+    // The same planes for things placed in the world by `mat` -> Culls in their own space
+    pub fn to_object_space(&self, mat: &Mat4x4) -> Self {
+        Frustum {
+            planes: self
+                .planes
+                .map(|plane| [0, 1, 2, 3].map(|i| (0..4).map(|j| mat[i][j] * plane[j]).sum())),
+        }
+    }
+
+    // Plane normals aren't unit length, especially after to_object_space -> Scale the radius
+    pub fn intersects_sphere(&self, center: &Vector3, radius: f32) -> bool {
+        self.planes.iter().all(|&[a, b, c, d]| {
+            let normal = Vector3::new(a, b, c);
+            let length = dot_product(&normal, &normal).sqrt();
+            dot_product(&normal, center) + d >= -radius * length
+        })
+    }
+
+    // Only the corner furthest along each plane's normal needs checking
+    pub fn intersects_box(&self, min: &Vector3, max: &Vector3) -> bool {
+        self.planes.iter().all(|&[a, b, c, d]| {
+            let corner = Vector3::new(
+                if a >= 0.0 { max.x } else { min.x },
+                if b >= 0.0 { max.y } else { min.y },
+                if c >= 0.0 { max.z } else { min.z },
+            );
+            a * corner.x + b * corner.y + c * corner.z + d >= 0.0
+        })
+    }
+}
@@ -0,0 +1,335 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufRead, BufReader},
+};
+
+use macroquad::input::{
+    KeyCode, MouseButton, is_key_down, is_key_pressed, is_mouse_button_down,
+    is_mouse_button_pressed,
+};
+
+use crate::invalid_data;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    LookUp,
+    LookDown,
+    LookLeft,
+    LookRight,
+    RollLeft,
+    RollRight,
+    ToggleMouseLook,
+    ReleaseMouse,
+    ToggleFlightControls,
+    ToggleOrbit,
+    ToggleProjection,
+    ToggleWalk,
+    Jump,
+    // Held while dragging the orbit camera
+    OrbitRotate,
+    OrbitPan,
+    // Camera path recording and playback
+    RecordKeyframe,
+    ClearPath,
+    SavePath,
+    TogglePlayback,
+    PlaybackFaster,
+    PlaybackSlower,
+}
+
+impl Action {
+    pub const ALL: [Action; 27] = [
+        Action::MoveForward,
+        Action::MoveBackward,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::LookUp,
+        Action::LookDown,
+        Action::LookLeft,
+        Action::LookRight,
+        Action::RollLeft,
+        Action::RollRight,
+        Action::ToggleMouseLook,
+        Action::ReleaseMouse,
+        Action::ToggleFlightControls,
+        Action::ToggleOrbit,
+        Action::ToggleProjection,
+        Action::ToggleWalk,
+        Action::Jump,
+        Action::OrbitRotate,
+        Action::OrbitPan,
+        Action::RecordKeyframe,
+        Action::ClearPath,
+        Action::SavePath,
+        Action::TogglePlayback,
+        Action::PlaybackFaster,
+        Action::PlaybackSlower,
+    ];
+
+    // Name used in the config file
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::MoveForward => "move_forward",
+            Action::MoveBackward => "move_backward",
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::MoveUp => "move_up",
+            Action::MoveDown => "move_down",
+            Action::LookUp => "look_up",
+            Action::LookDown => "look_down",
+            Action::LookLeft => "look_left",
+            Action::LookRight => "look_right",
+            Action::RollLeft => "roll_left",
+            Action::RollRight => "roll_right",
+            Action::ToggleMouseLook => "toggle_mouse_look",
+            Action::ReleaseMouse => "release_mouse",
+            Action::ToggleFlightControls => "toggle_flight_controls",
+            Action::ToggleOrbit => "toggle_orbit",
+            Action::ToggleProjection => "toggle_projection",
+            Action::ToggleWalk => "toggle_walk",
+            Action::Jump => "jump",
+            Action::OrbitRotate => "orbit_rotate",
+            Action::OrbitPan => "orbit_pan",
+            Action::RecordKeyframe => "record_keyframe",
+            Action::ClearPath => "clear_path",
+            Action::SavePath => "save_path",
+            Action::TogglePlayback => "toggle_playback",
+            Action::PlaybackFaster => "playback_faster",
+            Action::PlaybackSlower => "playback_slower",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+impl Binding {
+    // Key names are the same as in KeyCode (W, Up, LeftShift, Key1...),
+    // mouse buttons are MouseLeft, MouseMiddle and MouseRight
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "MouseLeft" => Some(Binding::Mouse(MouseButton::Left)),
+            "MouseMiddle" => Some(Binding::Mouse(MouseButton::Middle)),
+            "MouseRight" => Some(Binding::Mouse(MouseButton::Right)),
+            _ => KEYS
+                .iter()
+                .find(|key| format!("{key:?}") == name)
+                .map(|&key| Binding::Key(key)),
+        }
+    }
+
+    fn is_down(&self) -> bool {
+        match *self {
+            Binding::Key(key) => is_key_down(key),
+            Binding::Mouse(button) => is_mouse_button_down(button),
+        }
+    }
+
+    fn is_pressed(&self) -> bool {
+        match *self {
+            Binding::Key(key) => is_key_pressed(key),
+            Binding::Mouse(button) => is_mouse_button_pressed(button),
+        }
+    }
+}
+
+// Keys that can be bound in the config file
+#[rustfmt::skip]
+const KEYS: &[KeyCode] = &[
+    KeyCode::A, KeyCode::B, KeyCode::C, KeyCode::D, KeyCode::E, KeyCode::F, KeyCode::G,
+    KeyCode::H, KeyCode::I, KeyCode::J, KeyCode::K, KeyCode::L, KeyCode::M, KeyCode::N,
+    KeyCode::O, KeyCode::P, KeyCode::Q, KeyCode::R, KeyCode::S, KeyCode::T, KeyCode::U,
+    KeyCode::V, KeyCode::W, KeyCode::X, KeyCode::Y, KeyCode::Z,
+    KeyCode::Key0, KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4,
+    KeyCode::Key5, KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+    KeyCode::Up, KeyCode::Down, KeyCode::Left, KeyCode::Right,
+    KeyCode::Minus, KeyCode::Equal, KeyCode::Comma, KeyCode::Period, KeyCode::Slash,
+    KeyCode::Space, KeyCode::Tab, KeyCode::Escape, KeyCode::Enter, KeyCode::Backspace,
+    KeyCode::LeftShift, KeyCode::RightShift, KeyCode::LeftControl, KeyCode::RightControl,
+    KeyCode::LeftAlt, KeyCode::RightAlt,
+    KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6,
+    KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+    KeyCode::Kp0, KeyCode::Kp1, KeyCode::Kp2, KeyCode::Kp3, KeyCode::Kp4,
+    KeyCode::Kp5, KeyCode::Kp6, KeyCode::Kp7, KeyCode::Kp8, KeyCode::Kp9,
+];
+
+// Maps actions to the keys and mouse buttons that trigger them, along with how fast they move
+// the camera
+pub struct InputMap {
+    bindings: HashMap<Action, Vec<Binding>>,
+    // Units per second
+    pub move_speed: f32,
+    // Radians per second when turning with keys
+    pub turn_speed: f32,
+    // Radians turned when the mouse moves across half of the screen
+    pub mouse_sensitivity: f32,
+    pub invert_y: bool,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        use Binding::{Key, Mouse};
+
+        let bindings = [
+            (Action::MoveForward, vec![Key(KeyCode::W)]),
+            (Action::MoveBackward, vec![Key(KeyCode::S)]),
+            (Action::MoveLeft, vec![Key(KeyCode::A)]),
+            (Action::MoveRight, vec![Key(KeyCode::D)]),
+            (Action::MoveUp, vec![Key(KeyCode::Space)]),
+            (Action::MoveDown, vec![Key(KeyCode::LeftShift)]),
+            (Action::LookUp, vec![Key(KeyCode::Up)]),
+            (Action::LookDown, vec![Key(KeyCode::Down)]),
+            (Action::LookLeft, vec![Key(KeyCode::Left)]),
+            (Action::LookRight, vec![Key(KeyCode::Right)]),
+            (Action::RollLeft, vec![Key(KeyCode::Q)]),
+            (Action::RollRight, vec![Key(KeyCode::E)]),
+            (Action::ToggleMouseLook, vec![Key(KeyCode::M)]),
+            (Action::ReleaseMouse, vec![Key(KeyCode::Escape)]),
+            (Action::ToggleFlightControls, vec![Key(KeyCode::F)]),
+            (Action::ToggleOrbit, vec![Key(KeyCode::Tab)]),
+            (Action::ToggleProjection, vec![Key(KeyCode::P)]),
+            (Action::ToggleWalk, vec![Key(KeyCode::G)]),
+            (Action::Jump, vec![Key(KeyCode::Space)]),
+            (Action::OrbitRotate, vec![Mouse(MouseButton::Left)]),
+            (Action::OrbitPan, vec![Mouse(MouseButton::Middle)]),
+            (Action::RecordKeyframe, vec![Key(KeyCode::R)]),
+            (Action::ClearPath, vec![Key(KeyCode::Backspace)]),
+            (Action::SavePath, vec![Key(KeyCode::F5)]),
+            (Action::TogglePlayback, vec![Key(KeyCode::Enter)]),
+            (Action::PlaybackFaster, vec![Key(KeyCode::Equal)]),
+            (Action::PlaybackSlower, vec![Key(KeyCode::Minus)]),
+        ];
+
+        InputMap {
+            bindings: HashMap::from(bindings),
+            move_speed: 15.0,
+            turn_speed: 1.5,
+            mouse_sensitivity: 1.5,
+            invert_y: false,
+        }
+    }
+}
+
+impl InputMap {
+    // Reads a small subset of TOML: a [camera] table with the speeds and a [bindings] table
+    // mapping action names to a key name or a list of them. Anything left out keeps its default
+    //
+    // [camera]
+    // move_speed = 15.0
+    // invert_y = true
+    //
+    // [bindings]
+    // move_forward = ["W", "Up"]
+    // move_up = "Space"
+    pub fn load(path: &str) -> io::Result<Self> {
+        let mut input = InputMap::default();
+
+        let file = File::open(path)?;
+        let reader = BufReader::new(file);
+
+        let mut section = String::new();
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            let error = |message: &str| invalid_data(&format!("{path}:{}: {message}", number + 1));
+
+            // Strip comments -> Quoted names never contain a #
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+                section = name.trim().to_string();
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(error("expected key = value"));
+            };
+            let (key, value) = (key.trim(), value.trim());
+
+            match section.as_str() {
+                "camera" => match key {
+                    "move_speed" => input.move_speed = parse_number(value).ok_or(error(value))?,
+                    "turn_speed" => input.turn_speed = parse_number(value).ok_or(error(value))?,
+                    "mouse_sensitivity" => {
+                        input.mouse_sensitivity = parse_number(value).ok_or(error(value))?
+                    }
+                    "invert_y" => {
+                        input.invert_y = value.parse().map_err(|_| error(value))?;
+                    }
+                    _ => return Err(error(&format!("unknown camera setting {key}"))),
+                },
+                "bindings" => {
+                    let Some(action) = Action::ALL.into_iter().find(|a| a.name() == key) else {
+                        return Err(error(&format!("unknown action {key}")));
+                    };
+                    let bindings = parse_strings(value)
+                        .ok_or(error(value))?
+                        .iter()
+                        .map(|name| {
+                            Binding::parse(name).ok_or(error(&format!("unknown key {name}")))
+                        })
+                        .collect::<io::Result<Vec<_>>>()?;
+                    input.bind(action, bindings);
+                }
+                _ => return Err(error(&format!("unknown section [{section}]"))),
+            }
+        }
+
+        Ok(input)
+    }
+
+    pub fn bind(&mut self, action: Action, bindings: Vec<Binding>) {
+        self.bindings.insert(action, bindings);
+    }
+
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    // Held down this frame
+    pub fn is_down(&self, action: Action) -> bool {
+        self.bindings(action).iter().any(Binding::is_down)
+    }
+
+    // Went down this frame
+    pub fn is_pressed(&self, action: Action) -> bool {
+        self.bindings(action).iter().any(Binding::is_pressed)
+    }
+}
+
+fn parse_number(value: &str) -> Option<f32> {
+    value.parse().ok()
+}
+
+// Either a single quoted string or an array of them
+fn parse_strings(value: &str) -> Option<Vec<String>> {
+    let items = match value.strip_prefix('[') {
+        Some(array) => array.strip_suffix(']')?.split(',').collect(),
+        None => vec![value],
+    };
+
+    items
+        .into_iter()
+        .map(str::trim)
+        // Allow a trailing comma
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            let item = item.strip_prefix('"')?.strip_suffix('"')?;
+            Some(item.to_string())
+        })
+        .collect()
+}
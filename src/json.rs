@@ -0,0 +1,434 @@
+// Just enough JSON for the scene files -> Numbers are read as f32 and objects keep their key order
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Number(f32),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    // Errors name the line they happened on
+    pub(crate) fn parse(text: &str) -> Result<Json, String> {
+        let mut parser = Parser {
+            chars: text.chars().collect(),
+            index: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.index < parser.chars.len() {
+            return Err(parser.error("unexpected text after the end"));
+        }
+        Ok(value)
+    }
+
+    pub(crate) fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_f32(&self) -> Option<f32> {
+        match self {
+            Json::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_object(&self) -> Option<&[(String, Json)]> {
+        match self {
+            Json::Object(fields) => Some(fields),
+            _ => None,
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    index: usize,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> String {
+        let line = self.chars[..self.index.min(self.chars.len())]
+            .iter()
+            .filter(|&&c| c == '\n')
+            .count();
+        format!("line {}: {message}", line + 1)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.index += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.peek() != Some(c) {
+            return Err(self.error(&format!("expected '{c}'")));
+        }
+        self.index += 1;
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some('-' | '0'..='9') => self.number(),
+            Some(_) => {
+                let rest = self.chars[self.index..].iter().take(5).collect::<String>();
+                for (word, value) in [
+                    ("true", Json::Bool(true)),
+                    ("false", Json::Bool(false)),
+                    ("null", Json::Null),
+                ] {
+                    if rest.starts_with(word) {
+                        self.index += word.len();
+                        return Ok(value);
+                    }
+                }
+                Err(self.error("expected a value"))
+            }
+            None => Err(self.error("unexpected end of file")),
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut fields = Vec::new();
+
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.index += 1;
+            return Ok(Json::Object(fields));
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error("expected a key in quotes"));
+            }
+            let key = self.string()?;
+            if fields.iter().any(|(k, _)| *k == key) {
+                return Err(self.error(&format!("duplicate key {key}")));
+            }
+            self.expect(':')?;
+            fields.push((key, self.value()?));
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.index += 1,
+                Some('}') => {
+                    self.index += 1;
+                    return Ok(Json::Object(fields));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.index += 1;
+            return Ok(Json::Array(items));
+        }
+
+        loop {
+            items.push(self.value()?);
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.index += 1,
+                Some(']') => {
+                    self.index += 1;
+                    return Ok(Json::Array(items));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        // Skip the opening quote
+        self.index += 1;
+        let mut string = String::new();
+
+        loop {
+            let Some(c) = self.peek() else {
+                return Err(self.error("unterminated string"));
+            };
+            self.index += 1;
+
+            match c {
+                '"' => return Ok(string),
+                '\\' => {
+                    let Some(escaped) = self.peek() else {
+                        return Err(self.error("unterminated string"));
+                    };
+                    self.index += 1;
+                    string.push(match escaped {
+                        '"' | '\\' | '/' => escaped,
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'u' => self.unicode_escape()?,
+                        _ => return Err(self.error(&format!("invalid escape \\{escaped}"))),
+                    });
+                }
+                // Control characters have to be escaped
+                c if c < ' ' => return Err(self.error("unescaped control character in string")),
+                _ => string.push(c),
+            }
+        }
+    }
+
+    // The XXXX of \uXXXX, already past the u. Characters outside the basic multilingual plane
+    // are written as two escapes, eg: \ud83d\ude00
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.hex4()?;
+        let code = match high {
+            0xd800..=0xdbff => {
+                if self.peek() != Some('\\') || self.chars.get(self.index + 1) != Some(&'u') {
+                    return Err(self.error("unpaired surrogate in \\u escape"));
+                }
+                self.index += 2;
+                let low = self.hex4()?;
+                if !(0xdc00..=0xdfff).contains(&low) {
+                    return Err(self.error("unpaired surrogate in \\u escape"));
+                }
+                0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+            }
+            0xdc00..=0xdfff => return Err(self.error("unpaired surrogate in \\u escape")),
+            _ => high,
+        };
+        char::from_u32(code).ok_or_else(|| self.error("invalid \\u escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self
+                .peek()
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| self.error("invalid \\u escape"))?;
+            code = code * 16 + digit;
+            self.index += 1;
+        }
+        Ok(code)
+    }
+
+    // -?(0|[1-9][0-9]*)(.[0-9]+)?([eE][+-]?[0-9]+)?
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.index;
+        let valid = self.number_grammar()
+            // Eg: 01 or 1.2.3 -> Stopped before the end of what was meant as a number
+            && !self
+                .peek()
+                .is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '+' | '-'));
+
+        if !valid {
+            while self
+                .peek()
+                .is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '+' | '-'))
+            {
+                self.index += 1;
+            }
+            let text = self.chars[start..self.index].iter().collect::<String>();
+            return Err(self.error(&format!("invalid number {text}")));
+        }
+
+        let text = self.chars[start..self.index].iter().collect::<String>();
+        text.parse()
+            .map(Json::Number)
+            .map_err(|_| self.error(&format!("invalid number {text}")))
+    }
+
+    // Steps over a number, returning false where it stops following the grammar
+    fn number_grammar(&mut self) -> bool {
+        if self.peek() == Some('-') {
+            self.index += 1;
+        }
+        match self.peek() {
+            Some('0') => self.index += 1,
+            Some('1'..='9') => {
+                self.digits();
+            }
+            _ => return false,
+        }
+        if self.peek() == Some('.') {
+            self.index += 1;
+            if self.digits() == 0 {
+                return false;
+            }
+        }
+        if matches!(self.peek(), Some('e' | 'E')) {
+            self.index += 1;
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.index += 1;
+            }
+            if self.digits() == 0 {
+                return false;
+            }
+        }
+        true
+    }
+
+    // Steps over a run of digits and returns how many there were
+    fn digits(&mut self) -> usize {
+        let start = self.index;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.index += 1;
+        }
+        self.index - start
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Json;
+
+    fn string(text: &str) -> String {
+        match Json::parse(text) {
+            Ok(Json::String(string)) => string,
+            other => panic!("{text} parsed as {other:?}"),
+        }
+    }
+
+    fn number(text: &str) -> f32 {
+        match Json::parse(text) {
+            Ok(Json::Number(number)) => number,
+            other => panic!("{text} parsed as {other:?}"),
+        }
+    }
+
+    #[test]
+    fn reads_escapes() {
+        assert_eq!(
+            string(r#""\" \\ \/ \n \t \r \b \f""#),
+            "\" \\ / \n \t \r \u{8} \u{c}"
+        );
+        assert_eq!(string(r#""\u00e9\u4E2D""#), "é中");
+        assert_eq!(string(r#""\ud83d\ude00""#), "😀");
+    }
+
+    #[test]
+    fn rejects_invalid_escapes() {
+        for text in [
+            r#""\x""#,
+            r#""\u12""#,
+            r#""\u+123""#,
+            r#""\u12g4""#,
+            r#""\ud83d""#,
+            r#""\ude00""#,
+            "\"a\nb\"",
+        ] {
+            assert!(Json::parse(text).is_err(), "{text} parsed");
+        }
+    }
+
+    #[test]
+    fn reads_numbers() {
+        assert_eq!(number("0"), 0.0);
+        assert_eq!(number("-12"), -12.0);
+        assert_eq!(number("3.25"), 3.25);
+        assert_eq!(number("-0.5e2"), -50.0);
+        assert_eq!(number("1E+2"), 100.0);
+        assert_eq!(number("25e-1"), 2.5);
+    }
+
+    #[test]
+    fn rejects_numbers_outside_the_grammar() {
+        for text in [
+            "1.", ".5", "01", "-", "+1", "1-2e", "1e", "1e+", "1.2.3", "0x10", "-.5",
+        ] {
+            let err = Json::parse(text).expect_err(text);
+            assert!(
+                err.contains("invalid number") || err.contains("expected a value"),
+                "{err}"
+            );
+        }
+    }
+
+    #[test]
+    fn reads_nested_values_in_order() {
+        let json = Json::parse(r#"{ "b": [1, true, null], "a": { "c": "d" } }"#).unwrap();
+        let fields = json.as_object().unwrap();
+
+        assert_eq!(fields[0].0, "b");
+        assert_eq!(
+            json.get("b"),
+            Some(&Json::Array(vec![
+                Json::Number(1.0),
+                Json::Bool(true),
+                Json::Null
+            ]))
+        );
+        assert_eq!(
+            json.get("a")
+                .and_then(|a| a.get("c"))
+                .and_then(Json::as_str),
+            Some("d")
+        );
+    }
+
+    #[test]
+    fn rejects_duplicate_keys() {
+        let err = Json::parse(r#"{ "a": 1, "a": 2 }"#).unwrap_err();
+        assert!(err.contains("duplicate key a"), "{err}");
+    }
+
+    #[test]
+    fn rejects_trailing_text() {
+        for text in ["{} {}", "[1] x", "true false", "1 2"] {
+            let err = Json::parse(text).expect_err(text);
+            assert!(err.contains("unexpected text after the end"), "{err}");
+        }
+    }
+
+    #[test]
+    fn names_the_line_of_an_error() {
+        let err = Json::parse("{\n  \"a\": 1,\n  \"b\": tru\n}").unwrap_err();
+        assert!(err.starts_with("line 3: "), "{err}");
+
+        let err = Json::parse("[\n  1,\n\n  01\n]").unwrap_err();
+        assert_eq!(err, "line 4: invalid number 01");
+    }
+}
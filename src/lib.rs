@@ -1,37 +1,90 @@
+mod assets;
+mod bloom;
+mod bvh;
 mod camera;
+mod collision;
+mod fog;
+mod frustum;
+mod input;
+mod json;
+mod lod;
+mod material;
 pub mod matrix;
 mod mesh;
+mod path;
+mod primitives;
+mod scene;
+mod scene_file;
+mod simplify;
+mod skybox;
+mod terrain;
+mod watcher;
 
 // Re-export for the main file to use
-pub use crate::camera::Camera;
-pub use crate::mesh::Mesh;
+pub use crate::assets::Assets;
+pub use crate::bloom::Bloom;
+pub use crate::camera::{Camera, OrbitCamera, WalkController};
+pub use crate::collision::Collider;
+pub use crate::fog::{Fog, FogMode};
+pub use crate::frustum::Frustum;
+pub use crate::input::{Action, Binding, InputMap};
+pub use crate::lod::{Lod, LodLevel, LodMetric};
+pub use crate::material::{AlphaMode, Material};
+pub use crate::mesh::{Face, Instance, Mesh};
+pub use crate::path::{CameraPath, Keyframe};
+pub use crate::scene::{NodeId, Scene};
+pub use crate::scene_file::SceneFile;
+pub use crate::skybox::Skybox;
+pub use crate::watcher::FileWatcher;
 use macroquad::texture::Image;
 pub use matrix::Vector3;
+use std::rc::Rc;
 
 pub const FOV: f32 = 90.0;
 pub const NEAR: f32 = 0.1;
 pub const FAR: f32 = 100.0;
+// Depth buffer value for pixels nothing has been drawn to. Depths grow towards the camera
+pub const EMPTY_DEPTH: f32 = f32::NEG_INFINITY;
 
+// For files that were read but don't hold what they should, eg: a broken OBJ line
+pub(crate) fn invalid_data(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+// Meshes and textures are shared handles, eg: from Assets, so many objects can use the same ones
 pub struct Object {
-    pub mesh: Mesh,
+    pub mesh: Rc<Mesh>,
     pub position: Vector3,
     pub rotation: Vector3,
-    pub texture: Option<Image>,
+    // Per axis, applied before rotation -> Eg: (2.0, 1.0, 1.0) stretches the mesh along x
+    pub scale: Vector3,
+    pub texture: Option<Rc<Image>>,
+    pub material: Rc<Material>,
+    // Less detailed meshes to draw instead of `mesh` further away, see Scene::update_lods
+    pub lod: Option<Lod>,
 }
 
 impl Object {
-    // pub fn cube(position: Vector3) -> Self {
-    //     let mesh = Mesh::cube();
-    //     let rotation = Vector3::new(0.0, 0.0, 0.0);
-    //     Object {
-    //         mesh,
-    //         position,
-    //         rotation,
-    //     }
-    // }
+    // `mesh`, or a less detailed one picked by the last Lod::update
+    pub fn current_mesh(&self) -> &Rc<Mesh> {
+        self.lod
+            .as_ref()
+            .and_then(Lod::current_mesh)
+            .unwrap_or(&self.mesh)
+    }
+
+    // Scale, rotation and position as a matrix -> Relative to the parent when the object is in a
+    // Scene
+    pub fn local_matrix(&self) -> matrix::Mat4x4 {
+        matrix::transform_matrix(&self.scale, &self.rotation, &self.position)
+    }
 
+    // Returns false when the object was culled
+    #[allow(clippy::too_many_arguments)]
     pub fn draw(
         &self,
+        // Object space -> World space, eg: from Scene::world_matrix
+        transform_mat: &matrix::Mat4x4,
         width: f32,
         height: f32,
         camera: &Camera,
@@ -39,20 +92,27 @@ impl Object {
         projection_mat: &matrix::Mat4x4,
         view_mat: &matrix::Mat4x4,
         image: &mut Image,
-        depth_buffer: &mut Vec<f32>,
-    ) {
-        self.mesh.draw(
+        depth_buffer: &mut [f32],
+        emission_buffer: Option<&mut [[f32; 3]]>,
+        skybox: Option<&Skybox>,
+        fog: Option<&Fog>,
+    ) -> bool {
+        self.current_mesh().draw(
             width,
             height,
-            &self.rotation,
-            &self.position,
-            &view_mat,
+            transform_mat,
+            view_mat,
             &camera.position,
             light_direction,
             projection_mat,
+            camera.near,
             image,
-            &self.texture,
+            self.texture.as_deref(),
             depth_buffer,
-        );
+            emission_buffer,
+            &self.material,
+            skybox,
+            fog,
+        )
     }
 }
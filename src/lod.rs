@@ -0,0 +1,120 @@
+use std::rc::Rc;
+
+use crate::{
+    Camera, Mesh, Vector3,
+    matrix::{Mat4x4, dot_product, mult_vec_mat, vec_sub},
+};
+
+// What decides how much detail an object needs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LodMetric {
+    // Distance from Camera::position to the centre of the object's bounding sphere
+    Distance,
+    // Height of the bounding sphere on screen, as a fraction of the screen's height -> Follows the
+    // field of view and works with orthographic projections too
+    ScreenSize,
+}
+
+#[derive(Clone)]
+pub struct LodLevel {
+    pub mesh: Rc<Mesh>,
+    // Used once the object is further away than this with LodMetric::Distance, or smaller than
+    // this on screen with LodMetric::ScreenSize
+    pub switch_at: f32,
+}
+
+// Less detailed meshes for an object to use as it gets further away -> Eg: 50 teapots in the
+// distance don't need all of their faces
+#[derive(Clone)]
+pub struct Lod {
+    pub metric: LodMetric,
+    // From the most detailed to the least, below the object's own mesh
+    pub levels: Vec<LodLevel>,
+    // How far past a switch point to go before switching, as a fraction of it -> Eg: 0.1 keeps
+    // an object sitting right at a switch point from flickering between two meshes
+    pub hysteresis: f32,
+    // 0 for the object's own mesh, otherwise levels[current - 1]
+    current: usize,
+}
+
+impl Lod {
+    pub fn new(metric: LodMetric, levels: Vec<LodLevel>) -> Self {
+        Lod {
+            metric,
+            levels,
+            hysteresis: 0.0,
+            current: 0,
+        }
+    }
+
+    pub fn with_hysteresis(mut self, hysteresis: f32) -> Self {
+        self.hysteresis = hysteresis;
+        self
+    }
+
+    // 0 for the object's own mesh, 1 for the first level and so on
+    pub fn current_level(&self) -> usize {
+        self.current
+    }
+
+    // The mesh to draw at the current level, None for the object's own one
+    pub fn current_mesh(&self) -> Option<&Rc<Mesh>> {
+        self.current
+            .checked_sub(1)
+            .map(|level| &self.levels[level].mesh)
+    }
+
+    // Picks the level for an object placed by `transform_mat` whose own mesh is `mesh`. The
+    // bounding sphere of the most detailed mesh is used, so every level switches at the same place
+    pub fn update(&mut self, mesh: &Mesh, transform_mat: &Mat4x4, camera: &Camera) {
+        let (center, radius) = mesh.bounding_sphere();
+        let center = mult_vec_mat(&center, transform_mat);
+        // Longest scaled axis -> The sphere still contains the mesh under non-uniform scale
+        let scale = (0..3)
+            .map(|i| {
+                Vector3::new(
+                    transform_mat[i][0],
+                    transform_mat[i][1],
+                    transform_mat[i][2],
+                )
+            })
+            .map(|axis| dot_product(&axis, &axis).sqrt())
+            .fold(0.0, f32::max);
+        let radius = radius * scale;
+        let offset = vec_sub(&center, &camera.position);
+        let distance = dot_product(&offset, &offset).sqrt();
+
+        let value = match self.metric {
+            LodMetric::Distance => distance,
+            LodMetric::ScreenSize if camera.orthographic => 2.0 * radius / camera.ortho_height,
+            LodMetric::ScreenSize => {
+                let half_fov = camera.fov.to_radians() / 2.0;
+                radius / (distance * half_fov.tan())
+            }
+        };
+        self.select(value);
+    }
+
+    fn select(&mut self, value: f32) {
+        // How many switch points `value` is past once they are pushed back by `margin` -> Switch
+        // points are in order, so that is also the level
+        let levels_past = |margin: f32| {
+            self.levels
+                .iter()
+                .take_while(|level| match self.metric {
+                    LodMetric::Distance => value > level.switch_at * (1.0 + margin),
+                    LodMetric::ScreenSize => value < level.switch_at * (1.0 - margin),
+                })
+                .count()
+        };
+
+        // Only lose detail once clearly past a switch point, and only gain it back once clearly
+        // in front of it again -> In between, stay on the current level. Not clamp, which panics
+        // if switch points out of order make the bounds cross
+        let hysteresis = self.hysteresis.max(0.0);
+        self.current = self
+            .current
+            .max(levels_past(hysteresis))
+            .min(levels_past(-hysteresis));
+    }
+}
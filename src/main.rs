@@ -1,88 +1,393 @@
-use graphics_engine::{Camera, Mesh, Object, Vector3, matrix};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use graphics_engine::{
+    Action, Assets, Camera, CameraPath, Collider, EMPTY_DEPTH, FileWatcher, Fog, InputMap,
+    Instance, Object, OrbitCamera, Scene, SceneFile, Skybox, Vector3, WalkController,
+    matrix::{self, dot_product, vec_sub},
+};
 use macroquad::{
-    color::{BLACK, WHITE},
-    texture::{Image, Texture2D, draw_texture, load_image},
-    time::draw_fps,
+    color::{BLACK, Color, RED, WHITE},
+    text::draw_text,
+    texture::{Image, Texture2D, draw_texture},
+    time::{draw_fps, get_frame_time, get_time},
     window::{next_frame, screen_height, screen_width},
 };
 
+const CAMERA_PATH: &str = "camera_path.txt";
+// Shown when no scene file is given on the command line
+const DEFAULT_SCENE: &str = "assets/artisans_hub.json";
+// Seconds between checking the scene and its assets for changes on disk
+const RELOAD_INTERVAL: f64 = 0.5;
+
 #[macroquad::main("BasicShapes")]
 async fn main() {
-    let mesh = Mesh::load_from_obj("assets/map/Artisans Hub.obj", true).unwrap();
-    // let mesh = Mesh::cube();
-    let object = Object {
-        mesh,
-        position: Vector3::new(0.0, 0.0, 5.0),
-        rotation: Vector3::default(),
-        texture: Some(load_image("assets/map/High.png").await.unwrap()),
-        // texture: Some(load_image("assets/mario.png").await.unwrap()),
-        // texture: None,
+    // Eg: cargo run --release -- assets/artisans_hub.json
+    let scene_path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_SCENE.to_string());
+    // Meshes and textures loaded so far, shared between every object that uses them
+    let mut assets = Assets::new();
+    // Files that failed to load, shown on screen until they load again
+    let mut load_errors = Vec::new();
+    // A broken scene file starts out empty, to be filled in once it is fixed
+    let scene_file = SceneFile::load(&scene_path, &mut assets);
+    let mut scene_loaded = scene_file.is_ok();
+    let SceneFile {
+        mut scene,
+        mut camera,
+        mut light_direction,
+        mut skybox,
+        mut fog,
+        mut bloom,
+    } = report(&mut load_errors, Path::new(&scene_path), scene_file).unwrap_or_default();
+
+    // Editing the scene file or anything it loads shows up without restarting
+    let mut scene_watcher = FileWatcher::new();
+    scene_watcher.watch(Path::new(&scene_path));
+    let mut last_reload_check = get_time();
+
+    // Key bindings and camera speeds, falling back to the defaults without a config file
+    let input = match InputMap::load("input.toml") {
+        Ok(input) => input,
+        Err(err) => {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                eprintln!("Failed to load input.toml: {err}");
+            }
+            InputMap::default()
+        }
     };
 
-    let mut objs = vec![object];
+    // Tab switches to orbiting around the whole scene, framed to fit the view
+    let mut orbit_camera = OrbitCamera::default();
+    if let Some((min, max)) = scene_bounds(&scene) {
+        orbit_camera.frame(&min, &max, camera.fov);
+    }
+    let mut orbiting = false;
 
-    let mut camera = Camera::new();
+    // G walks around on the level instead of flying
+    let mut collider = Collider::from_scene(&scene);
+    let mut walker = WalkController::new();
+    let mut walking = false;
 
-    let light_direction = Vector3::new(0.0, 0.0, -1.0).normalize();
+    // Fly-through recorded earlier, if there is one. A file that is there but can't be read
+    // isn't saved over until there is something to replace it with
+    let mut camera_path_unread = false;
+    let mut camera_path = match CameraPath::load(CAMERA_PATH) {
+        Ok(camera_path) => camera_path,
+        Err(err) => {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                eprintln!("Failed to load {CAMERA_PATH}: {err}");
+                camera_path_unread = true;
+            }
+            CameraPath::default()
+        }
+    };
 
-    let projection_matrix =
-        matrix::projection_matrix(screen_width() / screen_height(), 90.0, 0.1, 100.0);
+    // Clear to the fog colour so fully fogged geometry blends into the background. A skybox
+    // is fogged the same way when it is drawn
+    let mut clear_color = fog.as_ref().map_or(BLACK, |fog| fog.color);
 
     let mut image = Image::gen_image_color(screen_width() as u16, screen_height() as u16, BLACK);
-    let img_texture = Texture2D::from_image(&image);
+    let mut img_texture = Texture2D::from_image(&image);
 
     loop {
+        if get_time() - last_reload_check >= RELOAD_INTERVAL {
+            last_reload_check = get_time();
+            let mut reloaded = false;
+            for (path, result) in assets.reload_changed(&mut scene, skybox.as_mut()) {
+                if report(&mut load_errors, &path, result).is_some() {
+                    println!("Reloaded {}", path.display());
+                    reloaded = true;
+                }
+            }
+
+            // Retried until it loads, as the file it is missing isn't being watched yet
+            if !scene_watcher.changed().is_empty() || !scene_loaded {
+                let scene_file = SceneFile::load(&scene_path, &mut assets);
+                // The camera stays where it is, to look at whatever changed
+                if let Some(scene_file) =
+                    report(&mut load_errors, Path::new(&scene_path), scene_file)
+                {
+                    scene = scene_file.scene;
+                    light_direction = scene_file.light_direction;
+                    skybox = scene_file.skybox;
+                    fog = scene_file.fog;
+                    bloom = scene_file.bloom;
+                    clear_color = fog.as_ref().map_or(BLACK, |fog| fog.color);
+                    println!("Reloaded {scene_path}");
+                    scene_loaded = true;
+                    reloaded = true;
+                } else {
+                    scene_loaded = false;
+                }
+            }
+
+            if reloaded {
+                collider = Collider::from_scene(&scene);
+            }
+        }
+
+        // Match the framebuffer to the window whenever it is resized
+        let (width, height) = (screen_width() as u16, screen_height() as u16);
+        if image.width() != width as usize || image.height() != height as usize {
+            image = Image::gen_image_color(width, height, BLACK);
+            img_texture = Texture2D::from_image(&image);
+        }
+
         // Reset depth buffer for next drawing
-        image.bytes.fill(0); // Clear the image to black efficiently
-        let mut depth_buffer = vec![0.0; (screen_width() * screen_height()) as usize];
+        clear(&mut image, clear_color);
+        let mut depth_buffer = vec![EMPTY_DEPTH; image.width() * image.height()];
+        // Only needed for bloom, which glows where emissive surfaces were drawn
+        let mut emission_buffer = bloom
+            .as_ref()
+            .map(|_| vec![[0.0; 3]; image.width() * image.height()]);
 
-        camera.handle_user_input();
-        tick(&mut objs);
-        draw(
-            &mut objs,
+        if input.is_pressed(Action::ToggleOrbit) {
+            orbiting = !orbiting;
+        }
+        if input.is_pressed(Action::ToggleWalk) {
+            walking = !walking;
+            walker.place_at(&camera);
+        }
+        if input.is_pressed(Action::ToggleProjection) {
+            camera.orthographic = !camera.orthographic;
+        }
+        handle_camera_path(&mut camera_path, &mut camera_path_unread, &camera, &input);
+        if camera_path.is_playing() {
+            camera_path.update(&mut camera, get_frame_time());
+        } else if orbiting {
+            orbit_camera.handle_user_input(&input);
+            orbit_camera.apply_to(&mut camera);
+        } else if walking {
+            camera.handle_look_input(&input);
+            walker.update(&mut camera, &input, &collider);
+        } else {
+            camera.handle_user_input(&input);
+        }
+        tick(&mut scene);
+        scene.update_transforms();
+        scene.update_lods(&camera);
+
+        let projection_matrix = camera.projection_matrix(width as f32 / height as f32);
+        let culled = draw(
+            &scene,
             &camera,
             &light_direction,
             &projection_matrix,
             &mut image,
             &mut depth_buffer,
+            emission_buffer.as_deref_mut(),
+            skybox.as_ref(),
+            fog.as_ref(),
         );
 
+        if let (Some(bloom), Some(emission_buffer)) = (&bloom, &emission_buffer) {
+            bloom.apply(&mut image, emission_buffer);
+        }
+
         img_texture.update(&image);
         draw_texture(&img_texture, 0., 0., WHITE);
 
         draw_fps();
+        draw_text(&format!("Culled: {culled}"), 0.0, 40.0, 32.0, WHITE);
+        for (i, (_, message)) in load_errors.iter().enumerate() {
+            draw_text(message, 0.0, 72.0 + i as f32 * 24.0, 24.0, RED);
+        }
 
         next_frame().await
     }
 }
 
-fn tick(objects: &mut Vec<Object>) {
-    for _object in objects {
+// Keeps the latest error for each file that failed to load, and forgets it once the file loads.
+// Only new errors are printed, as a broken scene file is retried over and over
+fn report<T>(
+    errors: &mut Vec<(PathBuf, String)>,
+    path: &Path,
+    result: std::io::Result<T>,
+) -> Option<T> {
+    let previous = errors.iter().position(|(p, _)| p == path);
+    match result {
+        Ok(value) => {
+            if let Some(i) = previous {
+                errors.remove(i);
+            }
+            Some(value)
+        }
+        Err(err) => {
+            let message = format!("Failed to load {}: {err}", path.display());
+            match previous {
+                Some(i) if errors[i].1 == message => {}
+                Some(i) => {
+                    eprintln!("{message}");
+                    errors[i].1 = message;
+                }
+                None => {
+                    eprintln!("{message}");
+                    errors.push((path.to_path_buf(), message));
+                }
+            }
+            None
+        }
+    }
+}
+
+// Records keyframes at the current view, plays them back and saves them for next time
+// `unread` is set while CAMERA_PATH holds a path that failed to load
+fn handle_camera_path(path: &mut CameraPath, unread: &mut bool, camera: &Camera, input: &InputMap) {
+    if input.is_pressed(Action::RecordKeyframe) {
+        path.record(camera);
+    }
+    if input.is_pressed(Action::ClearPath) {
+        path.clear();
+    }
+    if input.is_pressed(Action::TogglePlayback) {
+        if path.is_playing() {
+            path.stop();
+        } else {
+            path.play();
+        }
+    }
+    if input.is_pressed(Action::PlaybackFaster) {
+        path.speed *= 1.25;
+    }
+    if input.is_pressed(Action::PlaybackSlower) {
+        path.speed /= 1.25;
+    }
+    if input.is_pressed(Action::SavePath) {
+        if *unread && path.keyframes.is_empty() {
+            eprintln!("Not saving an empty path over {CAMERA_PATH}, which failed to load");
+        } else if let Err(err) = path.save(CAMERA_PATH) {
+            eprintln!("Failed to save {CAMERA_PATH}: {err}");
+        } else {
+            *unread = false;
+        }
+    }
+}
+
+fn clear(image: &mut Image, color: Color) {
+    let color: [u8; 4] = color.into();
+    for pixel in image.bytes.chunks_exact_mut(4) {
+        pixel.copy_from_slice(&color);
+    }
+}
+
+// Box around every object in the scene, if there are any
+fn scene_bounds(scene: &Scene) -> Option<(Vector3, Vector3)> {
+    scene
+        .iter()
+        .map(|(id, _, _)| scene.world_bounding_box(id))
+        .reduce(|(min1, max1), (min2, max2)| {
+            (
+                Vector3::new(min1.x.min(min2.x), min1.y.min(min2.y), min1.z.min(min2.z)),
+                Vector3::new(max1.x.max(max2.x), max1.y.max(max2.y), max1.z.max(max2.z)),
+            )
+        })
+}
+
+fn tick(scene: &mut Scene) {
+    for (_, _object) in scene.iter_mut() {
         // object.rotation.x += 0.005;
         // object.rotation.y += 0.01;
         // object.rotation.z += 0.01;
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn draw(
-    objects: &mut Vec<Object>,
+    scene: &Scene,
     camera: &Camera,
     light_direction: &Vector3,
     projection_mat: &matrix::Mat4x4,
     image: &mut Image,
-    depth_buffer: &mut Vec<f32>,
-) {
+    depth_buffer: &mut [f32],
+    mut emission_buffer: Option<&mut [[f32; 3]]>,
+    skybox: Option<&Skybox>,
+    fog: Option<&Fog>,
+) -> usize {
     let view_mat = camera.return_view_mat();
-    for object in objects {
-        object.draw(
-            screen_width(),
-            screen_height(),
+    // Objects skipped by frustum culling
+    let mut culled = 0;
+    let (width, height) = (image.width() as f32, image.height() as f32);
+
+    // Opaque objects first so transparent ones have something to blend over. Objects sharing a
+    // mesh, texture and material are drawn together as instances of it
+    let (mut transparent, opaque): (Vec<_>, Vec<_>) = scene
+        .iter()
+        .map(|(_, object, transform_mat)| (object, transform_mat))
+        .partition(|(object, _)| object.material.is_transparent());
+    let mut batches: Vec<(&Object, Vec<Instance>)> = Vec::new();
+    let mut batch_indices = HashMap::new();
+    for (object, transform_mat) in opaque {
+        let key = (
+            Rc::as_ptr(object.current_mesh()),
+            object.texture.as_ref().map_or(std::ptr::null(), Rc::as_ptr),
+            Rc::as_ptr(&object.material),
+        );
+        let index = *batch_indices.entry(key).or_insert_with(|| {
+            batches.push((object, Vec::new()));
+            batches.len() - 1
+        });
+        batches[index].1.push(Instance::new(*transform_mat));
+    }
+    for (object, instances) in batches {
+        culled += object.current_mesh().draw_instances(
+            width,
+            height,
+            &instances,
+            &view_mat,
+            &camera.position,
+            light_direction,
+            projection_mat,
+            camera.near,
+            image,
+            object.texture.as_deref(),
+            depth_buffer,
+            emission_buffer.as_deref_mut(),
+            &object.material,
+            skybox,
+            fog,
+        );
+    }
+
+    // Fill in the background wherever no geometry was drawn, before anything blends over it
+    if let Some(skybox) = skybox {
+        skybox.draw(image, depth_buffer, camera, projection_mat, fog);
+    }
+
+    // Then transparent objects from the furthest to the closest
+    let distance_to_camera = |(_, transform_mat): &(&Object, &matrix::Mat4x4)| {
+        let position = Vector3::new(
+            transform_mat[3][0],
+            transform_mat[3][1],
+            transform_mat[3][2],
+        );
+        let offset = vec_sub(&position, &camera.position);
+        dot_product(&offset, &offset)
+    };
+    transparent.sort_by(|o1, o2| distance_to_camera(o2).total_cmp(&distance_to_camera(o1)));
+    for (object, transform_mat) in transparent {
+        let drawn = object.draw(
+            transform_mat,
+            width,
+            height,
             camera,
             light_direction,
             projection_mat,
             &view_mat,
             image,
             depth_buffer,
+            emission_buffer.as_deref_mut(),
+            skybox,
+            fog,
         );
+        if !drawn {
+            culled += 1;
+        }
     }
+
+    culled
 }
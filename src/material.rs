@@ -0,0 +1,139 @@
+use std::{path::Path, rc::Rc};
+
+use macroquad::{
+    color::{BLACK, Color, WHITE},
+    texture::Image,
+};
+
+use crate::{Assets, invalid_data};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlphaMode {
+    // Alpha is ignored
+    Opaque,
+    // Pixels with alpha below the cutoff are discarded, everything else is opaque
+    Mask(f32),
+    // Drawn after opaque geometry, back-to-front, without writing depth
+    Blend,
+}
+
+// Surface properties of an object that are not stored in the mesh itself
+#[derive(Debug, Clone)]
+pub struct Material {
+    // How much of the skybox the surface mirrors: 0.0 = not at all, 1.0 = perfect mirror
+    pub reflectivity: f32,
+    // Multiplied with the texture's alpha -> The `d` (dissolve) value of an MTL file
+    pub opacity: f32,
+    pub alpha_mode: AlphaMode,
+    // Light given off by the surface, added on top of lighting -> Black for no glow
+    pub emissive: Color,
+    // Multiplied with `emissive`, sampled with the same texture coordinates as the main texture
+    pub emissive_texture: Option<Rc<Image>>,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            reflectivity: 0.0,
+            opacity: 1.0,
+            alpha_mode: AlphaMode::Opaque,
+            emissive: BLACK,
+            emissive_texture: None,
+        }
+    }
+}
+
+impl Material {
+    // Only the first material in the file is read, as a mesh has a single material. Textures it
+    // uses are loaded through `assets`, so they are shared with everything else using them
+    pub fn load_from_mtl(path: &str, assets: &mut Assets) -> Result<Self, std::io::Error> {
+        let mut material = Material::default();
+        let mut found_material = false;
+        let mut found_emissive = false;
+
+        let contents = std::fs::read_to_string(path)?;
+
+        for line in contents.lines() {
+            let mut parts = line.split_whitespace();
+            match parts.next() {
+                Some("newmtl") => {
+                    if found_material {
+                        break;
+                    }
+                    found_material = true;
+                }
+                // Eg: d 0.5
+                Some("d") => {
+                    material.opacity = parse_value(parts.next())?;
+                }
+                // Eg: Tr 0.5 -> Inverse of d
+                Some("Tr") => {
+                    material.opacity = 1.0 - parse_value(parts.next())?;
+                }
+                // Eg: Ke 1.0 0.5 0.0
+                Some("Ke") => {
+                    material.emissive = Color::new(
+                        parse_value(parts.next())?,
+                        parse_value(parts.next())?,
+                        parse_value(parts.next())?,
+                        1.0,
+                    );
+                    found_emissive = true;
+                }
+                // Eg: map_Ke glow.png -> Relative to the MTL file
+                Some("map_Ke") => {
+                    let file_name = line["map_Ke".len()..].trim();
+                    let texture_path = Path::new(path)
+                        .parent()
+                        .unwrap_or(Path::new(""))
+                        .join(file_name);
+                    material.emissive_texture = Some(assets.texture(&texture_path)?);
+                }
+                _ => {}
+            }
+        }
+
+        // A glow map on its own should glow at full strength
+        if material.emissive_texture.is_some() && !found_emissive {
+            material.emissive = WHITE;
+        }
+
+        if material.opacity < 1.0 {
+            material.alpha_mode = AlphaMode::Blend;
+        }
+
+        Ok(material)
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        self.alpha_mode == AlphaMode::Blend
+    }
+
+    pub fn is_emissive(&self) -> bool {
+        self.emissive.r > 0.0 || self.emissive.g > 0.0 || self.emissive.b > 0.0
+    }
+}
+
+// Any image format macroquad can decode, eg: PNG
+pub(crate) fn load_texture(path: &Path) -> Result<Image, std::io::Error> {
+    let bytes = std::fs::read(path)?;
+    Image::from_file_with_format(&bytes, None)
+        .map_err(|_| invalid_data(&format!("invalid texture {}", path.display())))
+}
+
+// Nearest texel at texture coordinates u, v (0.0 - 1.0), clamped to the edges
+pub(crate) fn sample_texture(texture: &Image, u: f32, v: f32) -> Color {
+    let x = (u * texture.width() as f32) as u32;
+    let y = (v * texture.height() as f32) as u32;
+
+    let x = x.clamp(0, texture.width().saturating_sub(1) as u32);
+    let y = y.clamp(0, texture.height().saturating_sub(1) as u32);
+
+    texture.get_pixel(x, y)
+}
+
+fn parse_value(value: Option<&str>) -> Result<f32, std::io::Error> {
+    value
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| invalid_data("invalid MTL value"))
+}
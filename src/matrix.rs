@@ -1,3 +1,5 @@
+use macroquad::color::Color;
+
 use crate::mesh::{Triangle, Vertex};
 
 #[derive(Debug, Clone, Copy)]
@@ -92,6 +94,132 @@ pub fn dot_product(vec1: &Vector3, vec2: &Vector3) -> f32 {
     vec1.x * vec2.x + vec1.y * vec2.y + vec1.z * vec2.z
 }
 
+// Linear interpolation: t = 0 gives v1, t = 1 gives v2
+pub fn vec_lerp(v1: &Vector3, v2: &Vector3, t: f32) -> Vector3 {
+    vec_add(v1, &vec_mul(&vec_sub(v2, v1), t))
+}
+
+pub fn color_lerp(c1: &Color, c2: &Color, t: f32) -> Color {
+    Color::from_vec(c1.to_vec().lerp(c2.to_vec(), t))
+}
+
+// Mirrors an incoming direction about a surface normal (normal should be normalized)
+pub fn reflect(incident: &Vector3, normal: &Vector3) -> Vector3 {
+    vec_sub(
+        incident,
+        &vec_mul(normal, 2.0 * dot_product(normal, incident)),
+    )
+}
+
+// Unit quaternion rotation -> Composes without gimbal lock and interpolates smoothly
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quaternion {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Quaternion {
+    pub fn identity() -> Self {
+        Quaternion {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+            w: 1.0,
+        }
+    }
+
+    // Turns by `angle` radians around `axis`, in the same direction as rotate_x/y/z do
+    pub fn from_axis_angle(axis: &Vector3, angle: f32) -> Self {
+        let axis = axis.normalize();
+        let (sin, cos) = (-angle / 2.0).sin_cos();
+        Quaternion {
+            x: axis.x * sin,
+            y: axis.y * sin,
+            z: axis.z * sin,
+            w: cos,
+        }
+    }
+
+    pub fn normalize(&self) -> Self {
+        let length = (self.x * self.x + self.y * self.y + self.z * self.z + self.w * self.w).sqrt();
+        Quaternion {
+            x: self.x / length,
+            y: self.y / length,
+            z: self.z / length,
+            w: self.w / length,
+        }
+    }
+
+    // The opposite rotation (for unit quaternions)
+    pub fn conjugate(&self) -> Self {
+        Quaternion {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+            w: self.w,
+        }
+    }
+}
+
+// Combined rotation that applies q2 first and q1 after it
+pub fn quat_multiply(q1: &Quaternion, q2: &Quaternion) -> Quaternion {
+    Quaternion {
+        x: q1.w * q2.x + q1.x * q2.w + q1.y * q2.z - q1.z * q2.y,
+        y: q1.w * q2.y - q1.x * q2.z + q1.y * q2.w + q1.z * q2.x,
+        z: q1.w * q2.z + q1.x * q2.y - q1.y * q2.x + q1.z * q2.w,
+        w: q1.w * q2.w - q1.x * q2.x - q1.y * q2.y - q1.z * q2.z,
+    }
+}
+
+pub fn quat_rotate(q: &Quaternion, v: &Vector3) -> Vector3 {
+    // v + 2w(u x v) + 2u x (u x v), where u is the vector part of q
+    let u = Vector3::new(q.x, q.y, q.z);
+    let uv = cross_product(&u, v);
+    let uuv = cross_product(&u, &uv);
+    vec_add(v, &vec_mul(&vec_add(&vec_mul(&uv, q.w), &uuv), 2.0))
+}
+
+// Spherical interpolation: turns from q1 to q2 at a constant speed the short way round
+pub fn quat_slerp(q1: &Quaternion, q2: &Quaternion, t: f32) -> Quaternion {
+    let mut q2 = *q2;
+    let mut dot = q1.x * q2.x + q1.y * q2.y + q1.z * q2.z + q1.w * q2.w;
+    // q and -q are the same rotation, so pick the one that is closer
+    if dot < 0.0 {
+        q2 = Quaternion {
+            x: -q2.x,
+            y: -q2.y,
+            z: -q2.z,
+            w: -q2.w,
+        };
+        dot = -dot;
+    }
+
+    // Nearly identical rotations -> Linear interpolation avoids dividing by sin(0)
+    let (s1, s2) = if dot > 0.9995 {
+        (1.0 - t, t)
+    } else {
+        let angle = dot.acos();
+        let sin = angle.sin();
+        (((1.0 - t) * angle).sin() / sin, (t * angle).sin() / sin)
+    };
+
+    Quaternion {
+        x: q1.x * s1 + q2.x * s2,
+        y: q1.y * s1 + q2.y * s2,
+        z: q1.z * s1 + q2.z * s2,
+        w: q1.w * s1 + q2.w * s2,
+    }
+    .normalize()
+}
+
 pub type Mat4x4 = [[f32; 4]; 4];
 
 pub fn mat_multiply(mat1: &Mat4x4, mat2: &Mat4x4) -> Mat4x4 {
@@ -123,6 +251,27 @@ pub fn projection_matrix(aspect_ratio: f32, fov: f32, near: f32, far: f32) -> Ma
     mat
 }
 
+// Maps a box of `width` by `height` centred on the view axis straight onto the screen, so
+// sizes don't shrink with distance. x and y are flipped the same way as in projection_matrix
+// and depth runs from 1.0 at the near plane to -1.0 at the far plane, so it shrinks with
+// distance like the perspective depth does
+pub fn orthographic_matrix(width: f32, height: f32, near: f32, far: f32) -> Mat4x4 {
+    let mut mat = [[0f32; 4]; 4];
+
+    mat[0][0] = -2.0 / width;
+    mat[1][1] = -2.0 / height;
+    mat[2][2] = -2.0 / (far - near);
+    mat[3][2] = (far + near) / (far - near);
+    mat[3][3] = 1.0;
+
+    mat
+}
+
+// Orthographic projections leave w at 1.0 instead of copying the depth into it
+pub fn is_orthographic(projection_mat: &Mat4x4) -> bool {
+    projection_mat[2][3] == 0.0
+}
+
 pub fn rotate_x(a: f32) -> Mat4x4 {
     let mut mat = [[0.0; 4]; 4];
     mat[0][0] = 1.0;
@@ -168,6 +317,29 @@ pub fn translate(x: f32, y: f32, z: f32) -> Mat4x4 {
     mat
 }
 
+pub fn scale(x: f32, y: f32, z: f32) -> Mat4x4 {
+    let mut mat = [[0.0; 4]; 4];
+    mat[0][0] = x;
+    mat[1][1] = y;
+    mat[2][2] = z;
+    mat[3][3] = 1.0;
+    mat
+}
+
+// Object space -> World space: scale, then rotation around x, then y, then z, then translation
+pub fn transform_matrix(scaling: &Vector3, rotation: &Vector3, translation: &Vector3) -> Mat4x4 {
+    let mat = mat_multiply(
+        &scale(scaling.x, scaling.y, scaling.z),
+        &rotate_x(rotation.x),
+    );
+    let mat = mat_multiply(&mat, &rotate_y(rotation.y));
+    let mat = mat_multiply(&mat, &rotate_z(rotation.z));
+    mat_multiply(
+        &mat,
+        &translate(translation.x, translation.y, translation.z),
+    )
+}
+
 pub fn point_at_mat(pos: &Vector3, target: &Vector3, up: &Vector3) -> Mat4x4 {
     // Getting the direction vectors of the camera
     let forward = (vec_sub(target, pos)).normalize();
@@ -232,6 +404,60 @@ pub fn quick_inverse_mat(mat: &Mat4x4) -> Mat4x4 {
     inv
 }
 
+// Works for any invertible matrix, unlike quick_inverse_mat which only undoes point_at_mat
+pub fn inverse_mat(mat: &Mat4x4) -> Mat4x4 {
+    // Gauss-Jordan elimination -> Whatever turns mat into the identity turns the identity into its inverse
+    let mut mat = *mat;
+    let mut inv = [[0.0; 4]; 4];
+    for (i, row) in inv.iter_mut().enumerate() {
+        row[i] = 1.0;
+    }
+
+    for column in 0..4 {
+        // Largest pivot keeps the division well behaved
+        let pivot = (column..4)
+            .max_by(|&a, &b| mat[a][column].abs().total_cmp(&mat[b][column].abs()))
+            .unwrap();
+        mat.swap(column, pivot);
+        inv.swap(column, pivot);
+
+        let scale = 1.0 / mat[column][column];
+        for j in 0..4 {
+            mat[column][j] *= scale;
+            inv[column][j] *= scale;
+        }
+
+        for row in 0..4 {
+            if row == column {
+                continue;
+            }
+            let factor = mat[row][column];
+            for j in 0..4 {
+                mat[row][j] -= factor * mat[column][j];
+                inv[row][j] -= factor * inv[column][j];
+            }
+        }
+    }
+
+    inv
+}
+
+pub fn transpose_mat(mat: &Mat4x4) -> Mat4x4 {
+    let mut transposed = [[0.0; 4]; 4];
+    for (i, row) in transposed.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = mat[j][i];
+        }
+    }
+    transposed
+}
+
+// Takes normals through a transform -> The inverse-transpose keeps them perpendicular to the
+// surface when it is stretched unevenly. Use with w = 0.0 so translation is left out
+pub fn normal_matrix(mat: &Mat4x4) -> Mat4x4 {
+    transpose_mat(&inverse_mat(mat))
+}
+
 pub fn mult_vec_mat(vec: &Vector3, mat: &Mat4x4) -> Vector3 {
     let mut result = Vector3::new(0.0, 0.0, 0.0);
 
@@ -254,14 +480,14 @@ pub fn line_plane_intersection(
     let line_direction = vec_sub(line_end, line_start);
     let t =
         (d - dot_product(line_start, &plane_normal)) / dot_product(&plane_normal, &line_direction);
-    let intersection = vec_add(&line_start, &vec_mul(&line_direction, t));
+    let intersection = vec_add(line_start, &vec_mul(&line_direction, t));
     (intersection, t)
 }
 
 // This is signed -> Positive distance means the point is in front of the plane (relative to normal)
 fn dist_point_plane(point: &Vertex, plane_normal: &Vector3, plane_point: &Vector3) -> f32 {
-    return (plane_normal.x * point.x + plane_normal.y * point.y + plane_normal.z * point.z)
-        - dot_product(plane_normal, plane_point);
+    (plane_normal.x * point.x + plane_normal.y * point.y + plane_normal.z * point.z)
+        - dot_product(plane_normal, plane_point)
 }
 
 pub fn triangle_clip_plane(
@@ -278,17 +504,29 @@ pub fn triangle_clip_plane(
     let mut inside_texture_coords = Vec::with_capacity(3);
     let mut outside_texture_coords = Vec::with_capacity(3);
 
+    let mut inside_world_positions = Vec::with_capacity(3);
+    let mut outside_world_positions = Vec::with_capacity(3);
+
+    let mut inside_colors = Vec::with_capacity(3);
+    let mut outside_colors = Vec::with_capacity(3);
+
     for i in 0..triangle.vertices.len() {
         let vertex = &triangle.vertices[i];
         let texture_coords = &triangle.texture_coords[i];
+        let world_position = &triangle.world_positions[i];
+        let color = &triangle.colors[i];
 
-        let distance = dist_point_plane(vertex, &plane_normal, &plane_point);
+        let distance = dist_point_plane(vertex, &plane_normal, plane_point);
         if distance >= 0.0 {
             inside_points.push(vertex);
             inside_texture_coords.push(texture_coords);
+            inside_world_positions.push(world_position);
+            inside_colors.push(color);
         } else {
             outside_points.push(vertex);
             outside_texture_coords.push(texture_coords);
+            outside_world_positions.push(world_position);
+            outside_colors.push(color);
         }
     }
 
@@ -310,9 +548,9 @@ pub fn triangle_clip_plane(
         let mut new_triangle = *triangle;
 
         let (intersection1, t1) =
-            line_plane_intersection(&plane_normal, &plane_point, inside_point, outside_point1);
+            line_plane_intersection(&plane_normal, plane_point, inside_point, outside_point1);
         let (intersection2, t2) =
-            line_plane_intersection(&plane_normal, &plane_point, inside_point, outside_point2);
+            line_plane_intersection(&plane_normal, plane_point, inside_point, outside_point2);
 
         new_triangle.vertices[0] = *inside_point;
         new_triangle.vertices[1] = intersection1;
@@ -339,6 +577,16 @@ pub fn triangle_clip_plane(
             * (outside_texture_coords[1].w - inside_texture_coords[0].w)
             + inside_texture_coords[0].w;
 
+        new_triangle.world_positions[0] = *inside_world_positions[0];
+        new_triangle.world_positions[1] =
+            vec_lerp(inside_world_positions[0], outside_world_positions[0], t1);
+        new_triangle.world_positions[2] =
+            vec_lerp(inside_world_positions[0], outside_world_positions[1], t2);
+
+        new_triangle.colors[0] = *inside_colors[0];
+        new_triangle.colors[1] = color_lerp(inside_colors[0], outside_colors[0], t1);
+        new_triangle.colors[2] = color_lerp(inside_colors[0], outside_colors[1], t2);
+
         out_triangles.push(new_triangle);
 
         return 1;
@@ -353,7 +601,7 @@ pub fn triangle_clip_plane(
 
         // First triangle
         let (intersection1, t1) =
-            line_plane_intersection(&plane_normal, &plane_point, inside_point1, outside_point);
+            line_plane_intersection(&plane_normal, plane_point, inside_point1, outside_point);
 
         new_triangle1.vertices[0] = *inside_point1;
         new_triangle1.vertices[1] = *inside_point2;
@@ -371,9 +619,18 @@ pub fn triangle_clip_plane(
             * (outside_texture_coords[0].w - inside_texture_coords[0].w)
             + inside_texture_coords[0].w;
 
+        new_triangle1.world_positions[0] = *inside_world_positions[0];
+        new_triangle1.world_positions[1] = *inside_world_positions[1];
+        new_triangle1.world_positions[2] =
+            vec_lerp(inside_world_positions[0], outside_world_positions[0], t1);
+
+        new_triangle1.colors[0] = *inside_colors[0];
+        new_triangle1.colors[1] = *inside_colors[1];
+        new_triangle1.colors[2] = color_lerp(inside_colors[0], outside_colors[0], t1);
+
         // Second triangle
         let (intersection2, t2) =
-            line_plane_intersection(&plane_normal, &plane_point, inside_point2, outside_point);
+            line_plane_intersection(&plane_normal, plane_point, inside_point2, outside_point);
 
         new_triangle2.vertices[0] = *inside_point2;
         new_triangle2.vertices[2] = intersection1;
@@ -391,11 +648,20 @@ pub fn triangle_clip_plane(
             * (outside_texture_coords[0].w - inside_texture_coords[1].w)
             + inside_texture_coords[1].w;
 
+        new_triangle2.world_positions[0] = *inside_world_positions[1];
+        new_triangle2.world_positions[2] = new_triangle1.world_positions[2];
+        new_triangle2.world_positions[1] =
+            vec_lerp(inside_world_positions[1], outside_world_positions[0], t2);
+
+        new_triangle2.colors[0] = *inside_colors[1];
+        new_triangle2.colors[2] = new_triangle1.colors[2];
+        new_triangle2.colors[1] = color_lerp(inside_colors[1], outside_colors[0], t2);
+
         out_triangles.push(new_triangle1);
         out_triangles.push(new_triangle2);
 
         return 2;
     }
 
-    return 0;
+    0
 }
@@ -1,19 +1,24 @@
 use std::collections::VecDeque;
 
 use macroquad::{
-    color::Color,
-    math::Vec2,
+    color::{Color, WHITE},
+    math::{Vec2, Vec4},
     shapes::{draw_line, draw_triangle},
     texture::Image,
-    window::{screen_height, screen_width},
 };
 
 use crate::{
-    NEAR, Vector3,
+    AlphaMode, Fog, Material, Skybox, Vector3,
+    bvh::Bvh,
+    collision::{ray_bvh_intersection, ray_triangle_intersection},
+    frustum::Frustum,
+    invalid_data,
+    material::sample_texture,
     matrix::{
-        Mat4x4, Vector2, cross_product, dot_product, mat_multiply, mult_vec_mat, rotate_x,
-        rotate_y, rotate_z, translate, triangle_clip_plane, vec_div, vec_sub, vec2_div,
+        Mat4x4, Vector2, cross_product, dot_product, is_orthographic, mult_vec_mat, normal_matrix,
+        reflect, triangle_clip_plane, vec_add, vec_div, vec_lerp, vec_sub, vec2_div,
     },
+    simplify,
 };
 
 pub type Vertex = Vector3;
@@ -23,18 +28,84 @@ pub struct Triangle {
     pub vertices: [Vertex; 3],
     pub intensity: f32,
     pub texture_coords: [Vector2; 3],
+    // World space position of each vertex, kept for per-pixel effects after projection
+    pub world_positions: [Vector3; 3],
+    // World space face normal
+    pub normal: Vector3,
+    // Vertex colours, white if the mesh has none
+    pub colors: [Color; 3],
 }
 
 impl Triangle {
-    fn new(vertices: [Vertex; 3], intensity: f32, texture_coords: [Vector2; 3]) -> Self {
+    fn new(
+        vertices: [Vertex; 3],
+        intensity: f32,
+        texture_coords: [Vector2; 3],
+        world_positions: [Vector3; 3],
+        normal: Vector3,
+        colors: [Color; 3],
+    ) -> Self {
         Triangle {
             vertices,
             intensity,
             texture_coords,
+            world_positions,
+            normal,
+            colors,
         }
     }
 }
 
+// Per-vertex values interpolated across a triangle. Everything apart from w and z is stored
+// divided by the vertex's w, so dividing by the interpolated w gives the perspective-correct value
+#[derive(Debug, Clone, Copy)]
+struct Varyings {
+    // Depth after projection -> Already linear in screen space, so it's interpolated as is
+    z: f32,
+    u: f32,
+    v: f32,
+    w: f32,
+    world_position: Vector3,
+    color: Vec4,
+}
+
+impl Varyings {
+    fn from_vertex(triangle: &Triangle, i: usize) -> Self {
+        Varyings {
+            z: triangle.vertices[i].z,
+            u: triangle.texture_coords[i].u,
+            v: triangle.texture_coords[i].v,
+            // NOTE THAT THE W HERE IS FROM TEXTURE COORDINATES
+            w: triangle.texture_coords[i].w,
+            world_position: triangle.world_positions[i],
+            color: triangle.colors[i].to_vec(),
+        }
+    }
+
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        Varyings {
+            z: (1.0 - t) * self.z + t * other.z,
+            u: (1.0 - t) * self.u + t * other.u,
+            v: (1.0 - t) * self.v + t * other.v,
+            w: (1.0 - t) * self.w + t * other.w,
+            world_position: vec_lerp(&self.world_position, &other.world_position, t),
+            color: self.color.lerp(other.color, t),
+        }
+    }
+}
+
+// Everything needed to colour a pixel that is shared by the whole mesh
+struct Shading<'a> {
+    texture: Option<&'a Image>,
+    material: &'a Material,
+    skybox: Option<&'a Skybox>,
+    fog: Option<&'a Fog>,
+    camera_position: &'a Vector3,
+    // Direction the camera looks in -> Every pixel looks this way with an orthographic projection
+    view_forward: Vector3,
+    orthographic: bool,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Face {
     pub vertices: [usize; 3],
@@ -50,111 +121,385 @@ impl Face {
     }
 }
 
+// One copy of a mesh drawn by Mesh::draw_instances
+#[derive(Debug, Clone, Copy)]
+pub struct Instance {
+    // Object space -> World space
+    pub transform: Mat4x4,
+    // Multiplied with the mesh's colours and texture, eg: to vary the shade of each tree
+    pub tint: Option<Color>,
+}
+
+impl Instance {
+    pub fn new(transform: Mat4x4) -> Self {
+        Instance {
+            transform,
+            tint: None,
+        }
+    }
+
+    pub fn tinted(transform: Mat4x4, tint: Color) -> Self {
+        Instance {
+            transform,
+            tint: Some(tint),
+        }
+    }
+}
+
 pub struct Mesh {
-    vertices: Vec<Vertex>,
-    faces: Vec<Face>,
+    pub(crate) vertices: Vec<Vertex>,
+    pub(crate) faces: Vec<Face>,
+    // One colour per vertex, if the file had any
+    pub(crate) colors: Option<Vec<Color>>,
+    // Worked out once on load -> Lets the whole mesh be culled before transforming any vertex
+    bounding_box: (Vector3, Vector3),
+    bounding_sphere: (Vector3, f32),
+    // Tree of face bounding boxes -> Culls and ray casts skip faces a group at a time
+    bvh: Bvh,
 }
 
 impl Mesh {
+    pub(crate) fn new(vertices: Vec<Vertex>, faces: Vec<Face>, colors: Option<Vec<Color>>) -> Self {
+        let mut min = Vector3::new(f32::MAX, f32::MAX, f32::MAX);
+        let mut max = Vector3::new(f32::MIN, f32::MIN, f32::MIN);
+
+        for vertex in &vertices {
+            min = Vector3::new(
+                min.x.min(vertex.x),
+                min.y.min(vertex.y),
+                min.z.min(vertex.z),
+            );
+            max = Vector3::new(
+                max.x.max(vertex.x),
+                max.y.max(vertex.y),
+                max.z.max(vertex.z),
+            );
+        }
+
+        // Centred on the box, just big enough to reach the furthest vertex
+        let center = vec_div(&vec_add(&min, &max), 2.0);
+        let radius = vertices
+            .iter()
+            .map(|vertex| {
+                let offset = vec_sub(vertex, &center);
+                dot_product(&offset, &offset)
+            })
+            .fold(0.0, f32::max)
+            .sqrt();
+
+        let face_bounds = faces
+            .iter()
+            .map(|face| {
+                let [a, b, c] = face.vertices.map(|v| vertices[v]);
+                (
+                    Vector3::new(
+                        a.x.min(b.x).min(c.x),
+                        a.y.min(b.y).min(c.y),
+                        a.z.min(b.z).min(c.z),
+                    ),
+                    Vector3::new(
+                        a.x.max(b.x).max(c.x),
+                        a.y.max(b.y).max(c.y),
+                        a.z.max(b.z).max(c.z),
+                    ),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        Mesh {
+            bvh: Bvh::new(&face_bounds),
+            vertices,
+            faces,
+            colors,
+            bounding_box: (min, max),
+            bounding_sphere: (center, radius),
+        }
+    }
+
     pub fn load_from_obj(path: &str, has_texture: bool) -> Result<Self, std::io::Error> {
         let mut vertices = Vec::new();
         let mut faces = Vec::new();
         let mut texture_coords = Vec::new();
+        let mut colors = Vec::new();
+        let mut has_colors = false;
 
         let contents = std::fs::read_to_string(path)?;
         let lines = contents.lines();
 
         for line in lines {
+            let invalid_line = || invalid_data(&format!("invalid OBJ line: {line}"));
+
             match line.get(0..2).unwrap_or_default() {
                 "v " => {
                     let parts: Vec<&str> = line[2..].split_whitespace().collect();
-                    let vertex = Vector3::new(
-                        parts[0].parse().unwrap(),
-                        parts[1].parse().unwrap(),
-                        parts[2].parse().unwrap(),
-                    );
+                    let value = |i: usize| {
+                        parts
+                            .get(i)
+                            .and_then(|part| part.parse::<f32>().ok())
+                            .ok_or_else(invalid_line)
+                    };
+                    let vertex = Vector3::new(value(0)?, value(1)?, value(2)?);
 
-                    vertices.push(vertex)
+                    vertices.push(vertex);
+
+                    // Eg: v 1.0 2.0 3.0 0.5 0.5 0.5 -> Some exporters append a colour
+                    if parts.len() >= 6 {
+                        colors.push(Color::new(value(3)?, value(4)?, value(5)?, 1.0));
+                        has_colors = true;
+                    } else {
+                        colors.push(WHITE);
+                    }
                 }
                 "vt" => {
                     // Eg: vt 0.5 0.5
-                    let parts: Vec<&str> = line[3..].split_whitespace().collect();
-                    let t_c = Vector2::new(
-                        parts[0].parse::<f32>().unwrap(),
-                        1.0 - parts[1].parse::<f32>().unwrap(),
-                    );
+                    let parts: Vec<&str> = line[2..].split_whitespace().collect();
+                    let value = |i: usize| {
+                        parts
+                            .get(i)
+                            .and_then(|part| part.parse::<f32>().ok())
+                            .ok_or_else(invalid_line)
+                    };
+                    let t_c = Vector2::new(value(0)?, 1.0 - value(1)?);
 
                     texture_coords.push(t_c);
                 }
                 "f " => {
-                    // Eg: f 1/1 2/2 3/3 (if has_texture) else f 1 2 3
+                    // Eg: f 1 2 3, f 1/1 2/2 3/3 or f 1/1/1 2/2/2 3/3/3. Texture coordinates are
+                    // only used when there is a texture, but any of these forms can be read
                     let parts: Vec<&str> = line[2..].split_whitespace().collect();
-                    if has_texture {
-                        let face_data = parts
-                            .iter()
-                            .map(|part| {
-                                let mut split = part.split('/');
-                                let vertex = split.next().unwrap().parse::<usize>().unwrap() - 1;
-                                let texture = split.next().unwrap().parse::<usize>().unwrap() - 1;
-                                (vertex, texture)
-                            })
-                            .collect::<Vec<_>>();
-
-                        let face = Face::new(
-                            [face_data[0].0, face_data[1].0, face_data[2].0],
-                            [
-                                texture_coords[face_data[0].1],
-                                texture_coords[face_data[1].1],
-                                texture_coords[face_data[2].1],
-                            ],
-                        );
-                        faces.push(face);
-                        // Some f data has 4 vertices => Split it into two triangles
-                        if face_data.len() == 4 {
-                            let face_2 = Face::new(
-                                [face_data[2].0, face_data[3].0, face_data[0].0],
-                                [
-                                    texture_coords[face_data[2].1],
-                                    texture_coords[face_data[3].1],
-                                    texture_coords[face_data[0].1],
-                                ],
-                            );
-                            faces.push(face_2);
-                        }
-                    } else {
-                        let vertices = parts
-                            .iter()
-                            .map(|part| part.parse::<usize>().unwrap() - 1)
-                            .collect::<Vec<_>>();
-
-                        let face = Face::new(
-                            [vertices[0], vertices[1], vertices[2]],
-                            [
-                                Vector2::new(0.0, 0.0),
-                                Vector2::new(1.0, 0.0),
-                                Vector2::new(1.0, 1.0),
-                            ],
-                        );
-                        faces.push(face);
-
-                        if parts.len() == 4 {
-                            let face_2 = Face::new(
-                                [vertices[2], vertices[3], vertices[0]],
-                                [
-                                    Vector2::new(0.0, 0.0),
-                                    Vector2::new(1.0, 0.0),
-                                    Vector2::new(1.0, 1.0),
-                                ],
-                            );
-                            faces.push(face_2);
-                        }
+                    if parts.len() < 3 {
+                        return Err(invalid_line());
+                    }
+                    // Indices start at 1 in the file
+                    let index = |text: &str| {
+                        text.parse::<usize>()
+                            .ok()
+                            .and_then(|index| index.checked_sub(1))
+                            .ok_or_else(invalid_line)
+                    };
+
+                    let corners = parts
+                        .iter()
+                        .map(|part| {
+                            let mut split = part.split('/');
+                            let vertex = index(split.next().unwrap_or_default())?;
+                            // Left out, as in f 1//1 2//2 3//3, when the file has no coordinates
+                            let texture = match split.next() {
+                                Some(text) if has_texture && !text.is_empty() => Some(
+                                    texture_coords
+                                        .get(index(text)?)
+                                        .copied()
+                                        .ok_or_else(invalid_line)?,
+                                ),
+                                _ => None,
+                            };
+                            Ok((vertex, texture))
+                        })
+                        .collect::<Result<Vec<_>, std::io::Error>>()?;
+
+                    // Some f data has 4 vertices => Split it into two triangles
+                    let mut triangles = vec![[0, 1, 2]];
+                    if corners.len() == 4 {
+                        triangles.push([2, 3, 0]);
+                    }
+                    for triangle in triangles {
+                        let vertices = triangle.map(|i| corners[i].0);
+                        let texture_coords = match triangle.map(|i| corners[i].1) {
+                            [Some(a), Some(b), Some(c)] => [a, b, c],
+                            _ => placeholder_texture_coords(),
+                        };
+                        faces.push(Face::new(vertices, texture_coords));
                     }
                 }
                 _ => {}
             }
         }
 
-        Ok(Mesh { vertices, faces })
+        if faces
+            .iter()
+            .any(|face| face.vertices.iter().any(|&v| v >= vertices.len()))
+        {
+            return Err(invalid_data("OBJ face refers to a missing vertex"));
+        }
+
+        Ok(Mesh::new(vertices, faces, has_colors.then_some(colors)))
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len()
+    }
+
+    pub fn face_count(&self) -> usize {
+        self.faces.len()
+    }
+
+    pub fn vertices(&self) -> &[Vertex] {
+        &self.vertices
+    }
+
+    pub fn faces(&self) -> &[Face] {
+        &self.faces
+    }
+
+    // A copy with at most `target_face_count` faces that keeps the shape as close as it can, eg:
+    // for the lower levels of a Lod. Borders and UV seams are kept in place where possible, so it
+    // can stop short of the target rather than tear them
+    pub fn simplify(&self, target_face_count: usize) -> Mesh {
+        simplify::simplify(self, target_face_count)
+    }
+
+    // Smallest and largest corner of the box around all vertices
+    pub fn bounding_box(&self) -> (Vector3, Vector3) {
+        self.bounding_box
+    }
+
+    // Centre and radius of a sphere around all vertices
+    pub fn bounding_sphere(&self) -> (Vector3, f32) {
+        self.bounding_sphere
+    }
+
+    // First face hit by a ray in the mesh's own space -> Distance along `direction` and face index
+    pub fn intersect_ray(&self, origin: &Vector3, direction: &Vector3) -> Option<(f32, usize)> {
+        ray_bvh_intersection(&self.bvh, origin, direction, |index| {
+            let triangle = self.faces[index].vertices.map(|v| self.vertices[v]);
+            ray_triangle_intersection(origin, direction, &triangle)
+        })
+    }
+
+    // Only ASCII files are supported. Reads positions, and colours (red, green, blue, alpha) and
+    // texture coordinates (s, t or u, v) if the vertices have them
+    pub fn load_from_ply(path: &str) -> Result<Self, std::io::Error> {
+        let contents = std::fs::read_to_string(path)?;
+        let mut lines = contents.lines();
+
+        let mut vertex_count = 0;
+        let mut face_count = 0;
+        let mut vertex_properties = Vec::new();
+        let mut current_element = "";
+
+        // Eg: element vertex 8 / property float x / property uchar red / end_header
+        for line in lines.by_ref() {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            match parts.as_slice() {
+                ["format", format, ..] if *format != "ascii" => {
+                    return Err(invalid_data("only ASCII PLY files are supported"));
+                }
+                ["element", name, count] => {
+                    current_element = name;
+                    let count = count
+                        .parse::<usize>()
+                        .map_err(|_| invalid_data("invalid PLY element count"))?;
+                    match *name {
+                        "vertex" => vertex_count = count,
+                        "face" => face_count = count,
+                        _ => {}
+                    }
+                }
+                ["property", kind, name] if current_element == "vertex" => {
+                    vertex_properties.push((*name, *kind));
+                }
+                ["end_header"] => break,
+                _ => {}
+            }
+        }
+
+        let property = |name: &str| vertex_properties.iter().position(|(n, _)| *n == name);
+        let (Some(x), Some(y), Some(z)) = (property("x"), property("y"), property("z")) else {
+            return Err(invalid_data("PLY vertices need x, y and z"));
+        };
+        let color_properties = [property("red"), property("green"), property("blue")];
+        let alpha = property("alpha");
+        let uv = property("s")
+            .zip(property("t"))
+            .or(property("u").zip(property("v")));
+
+        // Integer colours go from 0 to 255, floating point ones from 0.0 to 1.0
+        let color_scale = match color_properties[0].map(|i| vertex_properties[i].1) {
+            Some("float") | Some("float32") | Some("double") | Some("float64") => 1.0,
+            _ => 255.0,
+        };
+
+        let mut vertices = Vec::with_capacity(vertex_count);
+        let mut colors = Vec::with_capacity(vertex_count);
+        let mut vertex_uvs = Vec::with_capacity(vertex_count);
+
+        for _ in 0..vertex_count {
+            let values = lines
+                .next()
+                .ok_or_else(|| invalid_data("PLY file has fewer vertices than declared"))?
+                .split_whitespace()
+                .map(|value| value.parse::<f32>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| invalid_data("invalid PLY vertex"))?;
+            let value = |i: usize| {
+                values
+                    .get(i)
+                    .copied()
+                    .ok_or_else(|| invalid_data("PLY vertex is missing a property"))
+            };
+
+            vertices.push(Vector3::new(value(x)?, value(y)?, value(z)?));
+
+            if let [Some(r), Some(g), Some(b)] = color_properties {
+                let a = match alpha {
+                    Some(a) => value(a)? / color_scale,
+                    None => 1.0,
+                };
+                colors.push(Color::new(
+                    value(r)? / color_scale,
+                    value(g)? / color_scale,
+                    value(b)? / color_scale,
+                    a,
+                ));
+            }
+
+            if let Some((u, v)) = uv {
+                vertex_uvs.push(Vector2::new(value(u)?, 1.0 - value(v)?));
+            }
+        }
+
+        let mut faces = Vec::with_capacity(face_count);
+        for _ in 0..face_count {
+            // Eg: 4 0 1 2 3 -> The number of vertices followed by their indices. Anything after
+            // them belongs to other face properties
+            let mut values = lines
+                .next()
+                .ok_or_else(|| invalid_data("PLY file has fewer faces than declared"))?
+                .split_whitespace();
+            let count = values
+                .next()
+                .and_then(|value| value.parse::<usize>().ok())
+                .ok_or_else(|| invalid_data("invalid PLY face"))?;
+            let indices = values
+                .take(count)
+                .map(|value| value.parse::<usize>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| invalid_data("invalid PLY face"))?;
+
+            if indices.len() < count {
+                return Err(invalid_data("PLY face has fewer vertices than its count"));
+            }
+            if indices.iter().any(|&i| i >= vertices.len()) {
+                return Err(invalid_data("PLY face refers to a missing vertex"));
+            }
+
+            // Split polygons into a fan of triangles
+            for i in 1..indices.len().saturating_sub(1) {
+                let face_vertices = [indices[0], indices[i], indices[i + 1]];
+                let texture_coords = if uv.is_some() {
+                    face_vertices.map(|v| vertex_uvs[v])
+                } else {
+                    placeholder_texture_coords()
+                };
+                faces.push(Face::new(face_vertices, texture_coords));
+            }
+        }
+
+        Ok(Mesh::new(
+            vertices,
+            faces,
+            (!colors.is_empty()).then_some(colors),
+        ))
     }
 
     pub fn cube() -> Self {
@@ -273,130 +618,240 @@ impl Mesh {
                 ],
             ),
         ];
-        Mesh { vertices, faces }
+        Mesh::new(vertices, faces, None)
     }
 
+    // Returns false when the mesh was entirely out of view and nothing was drawn
+    #[allow(clippy::too_many_arguments)]
     pub fn draw(
         &self,
         width: f32,
         height: f32,
-        rotation: &Vector3,
-        translation: &Vector3,
+        // Object space -> World space
+        transform_mat: &Mat4x4,
         view_mat: &Mat4x4,
         camera_position: &Vector3,
         light_direction: &Vector3,
         projection_mat: &Mat4x4,
+        near: f32,
         // For drawing on screen
         image: &mut Image,
-        texture: &Option<Image>,
-        depth_buffer: &mut Vec<f32>,
-    ) {
-        let mut triangles_to_raster = Vec::new();
-
-        // Pre-calculate the transformation matrix
-        let transform_mat = mat_multiply(&rotate_x(rotation.x), &rotate_y(rotation.y));
-        let transform_mat = mat_multiply(&transform_mat, &rotate_z(rotation.z));
-        let transform_mat = mat_multiply(
-            &transform_mat,
-            &translate(translation.x, translation.y, translation.z),
+        texture: Option<&Image>,
+        depth_buffer: &mut [f32],
+        // Emission of each pixel, for bloom to make glow from. Leave out when not using bloom
+        emission_buffer: Option<&mut [[f32; 3]]>,
+        material: &Material,
+        skybox: Option<&Skybox>,
+        fog: Option<&Fog>,
+    ) -> bool {
+        let instance = Instance::new(*transform_mat);
+        let culled = self.draw_instances(
+            width,
+            height,
+            std::slice::from_ref(&instance),
+            view_mat,
+            camera_position,
+            light_direction,
+            projection_mat,
+            near,
+            image,
+            texture,
+            depth_buffer,
+            emission_buffer,
+            material,
+            skybox,
+            fog,
         );
+        culled == 0
+    }
 
-        for face in &self.faces {
-            // Transform vertices -> Rotation, Translation, Scale (Not yet implemented)
-            let mut transformed_vertices = Vec::with_capacity(3);
-            for v in face.vertices {
-                let vertex = &self.vertices[v];
+    // Draws a copy of the mesh for each instance, eg: every tree in a forest. The camera, frustum
+    // and shading are set up once for all of them, and transparent faces are sorted together.
+    // Returns how many instances were culled
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_instances(
+        &self,
+        width: f32,
+        height: f32,
+        instances: &[Instance],
+        view_mat: &Mat4x4,
+        camera_position: &Vector3,
+        light_direction: &Vector3,
+        projection_mat: &Mat4x4,
+        near: f32,
+        // For drawing on screen
+        image: &mut Image,
+        texture: Option<&Image>,
+        depth_buffer: &mut [f32],
+        mut emission_buffer: Option<&mut [[f32; 3]]>,
+        material: &Material,
+        skybox: Option<&Skybox>,
+        fog: Option<&Fog>,
+    ) -> usize {
+        let mut triangles_to_raster = Vec::new();
+        let mut culled = 0;
+
+        // Camera axes in world space are the columns of the view matrix
+        let view_forward = Vector3::new(view_mat[0][2], view_mat[1][2], view_mat[2][2]);
+        let orthographic = is_orthographic(projection_mat);
+        let world_frustum = Frustum::new(view_mat, projection_mat, near);
+
+        for instance in instances {
+            let transform_mat = &instance.transform;
+
+            // Frustum culling -> Checked in object space, where the bounds still fit the mesh
+            // however it is scaled. Skip the whole instance if its bounding sphere is out of view,
+            // or failing that its tighter box, then only keep the faces in parts of the BVH that
+            // might be in view, in their original order
+            let frustum = world_frustum.to_object_space(transform_mat);
+            let (center, radius) = self.bounding_sphere;
+            let (min, max) = self.bounding_box;
+            if !frustum.intersects_sphere(&center, radius) || !frustum.intersects_box(&min, &max) {
+                culled += 1;
+                continue;
+            }
+            let mut visible_faces = self.bvh.query(|min, max| frustum.intersects_box(min, max));
+            visible_faces.sort_unstable();
 
-                let transformed = mult_vec_mat(&vertex, &transform_mat);
+            // Face normals go through the inverse-transpose -> Stays right under non-uniform scale
+            let normal_mat = normal_matrix(transform_mat);
 
-                transformed_vertices.push(transformed);
-            }
+            for face in visible_faces.into_iter().map(|index| &self.faces[index]) {
+                // Transform vertices -> Scale, Rotation, Translation
+                let mut transformed_vertices = Vec::with_capacity(3);
+                for v in face.vertices {
+                    let vertex = &self.vertices[v];
 
-            let v1 = &transformed_vertices[0];
-            let v2 = &transformed_vertices[1];
-            let v3 = &transformed_vertices[2];
-
-            // Check if face is visible
-            //
-            // Calculate the normal vector
-            let line1 = vec_sub(v2, v1);
-            let line2 = vec_sub(v3, v1);
-
-            let normal = cross_product(&line1, &line2).normalize();
-
-            // From camera to the normal -> Check if face is visible
-            let ray = vec_sub(v1, camera_position);
-            let normal_dot = dot_product(&normal, &ray);
-
-            // Render only if visible
-            if normal_dot < 0.0 {
-                // Calculate light intensity
-                let light_dot = dot_product(&normal, &light_direction);
-                let intensity = light_dot * 205.0 + 50.0;
-
-                // Convert World space -> View space
-                let view_triangle = Triangle::new(
-                    [
-                        mult_vec_mat(v1, view_mat),
-                        mult_vec_mat(v2, view_mat),
-                        mult_vec_mat(v3, view_mat),
-                    ],
-                    intensity,
-                    face.texture_coords,
-                );
+                    let transformed = mult_vec_mat(vertex, transform_mat);
 
-                // Clipping triangles against near plane
-                let mut clipped_triangles = Vec::with_capacity(2);
+                    transformed_vertices.push(transformed);
+                }
 
-                let _num_clipped_triangles = triangle_clip_plane(
-                    &Vector3::forward(),
-                    &Vector3::new(0.0, 0.0, NEAR),
-                    &view_triangle,
-                    &mut clipped_triangles,
-                );
+                let v1 = &transformed_vertices[0];
+                let v2 = &transformed_vertices[1];
+                let v3 = &transformed_vertices[2];
+
+                // Check if face is visible
+                //
+                // Calculate the normal vector in object space, then bring it into world space. Unlike
+                // the cross product of the transformed edges, this doesn't flip when a scale mirrors
+                // the mesh
+                let [o1, o2, o3] = face.vertices.map(|v| self.vertices[v]);
+                let line1 = vec_sub(&o2, &o1);
+                let line2 = vec_sub(&o3, &o1);
+
+                let mut normal = cross_product(&line1, &line2);
+                normal.w = 0.0;
+                let normal = mult_vec_mat(&normal, &normal_mat).normalize();
+
+                // From camera to the normal -> Check if face is visible
+                let ray = if orthographic {
+                    view_forward
+                } else {
+                    vec_sub(v1, camera_position)
+                };
+                let normal_dot = dot_product(&normal, &ray);
+
+                // Render only if visible
+                if normal_dot < 0.0 {
+                    // Calculate light intensity
+                    let light_dot = dot_product(&normal, light_direction);
+                    let intensity = light_dot * 205.0 + 50.0;
+
+                    // Convert World space -> View space
+                    let view_triangle = Triangle::new(
+                        [
+                            mult_vec_mat(v1, view_mat),
+                            mult_vec_mat(v2, view_mat),
+                            mult_vec_mat(v3, view_mat),
+                        ],
+                        intensity,
+                        face.texture_coords,
+                        [*v1, *v2, *v3],
+                        normal,
+                        face.vertices.map(|v| {
+                            let color = match &self.colors {
+                                Some(colors) => colors[v],
+                                None => WHITE,
+                            };
+                            match instance.tint {
+                                Some(tint) => Color::from_vec(color.to_vec() * tint.to_vec()),
+                                None => color,
+                            }
+                        }),
+                    );
 
-                // Project to screen: 3D -> 2D
-                for clipped_triangle in clipped_triangles {
-                    let mut projected_triangle = clipped_triangle.clone();
+                    // Clipping triangles against near plane
+                    let mut clipped_triangles = Vec::with_capacity(2);
 
-                    for i in 0..3 {
-                        // Project to screen
-                        let vertex = clipped_triangle.vertices[i];
-                        let texture_coords = clipped_triangle.texture_coords[i];
+                    let _num_clipped_triangles = triangle_clip_plane(
+                        &Vector3::forward(),
+                        &Vector3::new(0.0, 0.0, near),
+                        &view_triangle,
+                        &mut clipped_triangles,
+                    );
 
-                        let projected_vertex = mult_vec_mat(&vertex, projection_mat);
+                    // Project to screen: 3D -> 2D
+                    for clipped_triangle in clipped_triangles {
+                        let mut projected_triangle = clipped_triangle;
+
+                        for i in 0..3 {
+                            // Project to screen
+                            let vertex = clipped_triangle.vertices[i];
+                            let texture_coords = clipped_triangle.texture_coords[i];
+
+                            let projected_vertex = mult_vec_mat(&vertex, projection_mat);
+
+                            // Project texture coordinates (Make texture coordinates relative to z)
+                            projected_triangle.texture_coords[i] =
+                                vec2_div(&texture_coords, projected_vertex.w);
+                            // Idk why this is needed
+                            projected_triangle.texture_coords[i].w = 1.0 / projected_vertex.w;
+                            projected_triangle.world_positions[i] =
+                                vec_div(&clipped_triangle.world_positions[i], projected_vertex.w);
+                            projected_triangle.colors[i] = Color::from_vec(
+                                clipped_triangle.colors[i].to_vec() / projected_vertex.w,
+                            );
 
-                        // Project texture coordinates (Make texture coordinates relative to z)
-                        projected_triangle.texture_coords[i] =
-                            vec2_div(&texture_coords, projected_vertex.w);
-                        // Idk why this is needed
-                        projected_triangle.texture_coords[i].w = 1.0 / projected_vertex.w;
+                            // Normalize into cartesian coordinates using w component
+                            let mut projected_vertex =
+                                vec_div(&projected_vertex, projected_vertex.w);
 
-                        // Normalize into cartesian coordinates using w component
-                        let mut projected_vertex = vec_div(&projected_vertex, projected_vertex.w);
+                            // Scale to screen dimensions
+                            projected_vertex.x = (projected_vertex.x + 1.0) * width / 2.0;
+                            projected_vertex.y = (projected_vertex.y + 1.0) * height / 2.0;
 
-                        // Scale to screen dimensions
-                        projected_vertex.x = (projected_vertex.x + 1.0) * width / 2.0;
-                        projected_vertex.y = (projected_vertex.y + 1.0) * height / 2.0;
+                            projected_triangle.vertices[i] = projected_vertex;
+                        }
 
-                        projected_triangle.vertices[i] = projected_vertex;
+                        triangles_to_raster.push(projected_triangle);
                     }
-
-                    triangles_to_raster.push(projected_triangle);
                 }
             }
         }
 
-        // NO NEED FOR THIS SINCE WE'RE USING A DEPTH BUFFER
+        // Opaque triangles are sorted by the depth buffer, but blended ones don't write to it
         //
         // Sort triangles by average depth (painter's algorithm)
         // Render triangles in order of highest depth (z-index) to lowest
-        // triangles_to_raster.sort_by(|t1, t2| {
-        //     let z1 = (t1.vertices[0].z + t1.vertices[1].z + t1.vertices[2].z) / 3.0;
-        //     let z2 = (t2.vertices[0].z + t2.vertices[1].z + t2.vertices[2].z) / 3.0;
+        if material.is_transparent() {
+            triangles_to_raster.sort_by(|t1, t2| {
+                let z1 = (t1.vertices[0].z + t1.vertices[1].z + t1.vertices[2].z) / 3.0;
+                let z2 = (t2.vertices[0].z + t2.vertices[1].z + t2.vertices[2].z) / 3.0;
 
-        //     z1.partial_cmp(&z2).unwrap()
-        // });
+                z1.total_cmp(&z2)
+            });
+        }
+
+        let shading = Shading {
+            texture,
+            material,
+            skybox,
+            fog,
+            camera_position,
+            view_forward,
+            orthographic,
+        };
 
         for triangle in triangles_to_raster {
             // Clip triangle against screen boundaries
@@ -422,7 +877,7 @@ impl Mesh {
                             // Bottom plane
                             triangle_clip_plane(
                                 &Vector3::down(),
-                                &Vector3::new(0.0, screen_height() - 0.0, 0.0),
+                                &Vector3::new(0.0, height, 0.0),
                                 &triangle_to_clip,
                                 &mut clipped_triangles,
                             );
@@ -440,7 +895,7 @@ impl Mesh {
                             // Right plane
                             triangle_clip_plane(
                                 &Vector3::left(),
-                                &Vector3::new(screen_width() - 0.0, 0.0, 0.0),
+                                &Vector3::new(width, 0.0, 0.0),
                                 &triangle_to_clip,
                                 &mut clipped_triangles,
                             );
@@ -455,258 +910,216 @@ impl Mesh {
             }
 
             for clipped_triangle in triangle_queue {
-                Self::draw_textured_triangle(clipped_triangle, image, texture, depth_buffer);
+                Self::draw_textured_triangle(
+                    clipped_triangle,
+                    image,
+                    depth_buffer,
+                    emission_buffer.as_deref_mut(),
+                    &shading,
+                );
 
                 // Self::draw_triangle_face(clipped_triangle);
                 // Self::_draw_triangle_wireframe(clipped_triangle);
             }
         }
+
+        culled
     }
 
     fn draw_textured_triangle(
         triangle: Triangle,
         image: &mut Image,
-        texture: &Option<Image>,
-        depth_buffer: &mut Vec<f32>,
+        depth_buffer: &mut [f32],
+        mut emission_buffer: Option<&mut [[f32; 3]]>,
+        shading: &Shading,
     ) {
-        use std::mem::swap;
-        // Order vertices and texture coordinates by y-coordinate of vertex
-        let mut y1 = triangle.vertices[0].y as i32;
-        let mut y2 = triangle.vertices[1].y as i32;
-        let mut y3 = triangle.vertices[2].y as i32;
-        let mut x1 = triangle.vertices[0].x as i32;
-        let mut x2 = triangle.vertices[1].x as i32;
-        let mut x3 = triangle.vertices[2].x as i32;
-
-        let mut u1 = triangle.texture_coords[0].u;
-        let mut u2 = triangle.texture_coords[1].u;
-        let mut u3 = triangle.texture_coords[2].u;
-        let mut v1 = triangle.texture_coords[0].v;
-        let mut v2 = triangle.texture_coords[1].v;
-        let mut v3 = triangle.texture_coords[2].v;
-        // NOTE THAT THE W HERE ARE FROM TEXTURE COORDINATES
-        let mut w1 = triangle.texture_coords[0].w;
-        let mut w2 = triangle.texture_coords[1].w;
-        let mut w3 = triangle.texture_coords[2].w;
-
-        if y2 < y1 {
-            swap(&mut y1, &mut y2);
-            swap(&mut x1, &mut x2);
-            swap(&mut u1, &mut u2);
-            swap(&mut v1, &mut v2);
-            swap(&mut w1, &mut w2);
-        }
+        // Order vertices by y-coordinate of vertex
+        let mut order = [0, 1, 2];
+        order.sort_by_key(|&i| triangle.vertices[i].y as i32);
 
-        if y3 < y1 {
-            swap(&mut y1, &mut y3);
-            swap(&mut x1, &mut x3);
-            swap(&mut u1, &mut u3);
-            swap(&mut v1, &mut v3);
-            swap(&mut w1, &mut w3);
+        // These are integers as the number represents pixels, which cannot be floats
+        let [(x1, y1), (x2, y2), (x3, y3)] =
+            order.map(|i| (triangle.vertices[i].x as i32, triangle.vertices[i].y as i32));
+        let [varyings1, varyings2, varyings3] = order.map(|i| Varyings::from_vertex(&triangle, i));
+
+        // First half of the triangle -> Sides A (1 to 2) and B (1 to 3)
+        for i in y1..y2 {
+            let ta = (i - y1) as f32 / (y2 - y1) as f32;
+            let tb = (i - y1) as f32 / (y3 - y1) as f32;
+
+            // Ax and Bx are the starting and ending x values in a scanline repectively
+            let ax = (x1 as f32 + (x2 - x1) as f32 * ta) as i32;
+            let bx = (x1 as f32 + (x3 - x1) as f32 * tb) as i32;
+
+            Self::draw_scanline(
+                &triangle,
+                i,
+                (ax, varyings1.lerp(&varyings2, ta)),
+                (bx, varyings1.lerp(&varyings3, tb)),
+                image,
+                depth_buffer,
+                emission_buffer.as_deref_mut(),
+                shading,
+            );
         }
 
-        if y3 < y2 {
-            swap(&mut y2, &mut y3);
-            swap(&mut x2, &mut x3);
-            swap(&mut u2, &mut u3);
-            swap(&mut v2, &mut v3);
-            swap(&mut w2, &mut w3);
+        // Second half of the triangle -> Sides A (2 to 3) and B (1 to 3)
+        for i in y2..y3 {
+            let ta = (i - y2) as f32 / (y3 - y2) as f32;
+            let tb = (i - y1) as f32 / (y3 - y1) as f32;
+
+            let ax = (x2 as f32 + (x3 - x2) as f32 * ta) as i32;
+            let bx = (x1 as f32 + (x3 - x1) as f32 * tb) as i32;
+
+            Self::draw_scanline(
+                &triangle,
+                i,
+                (ax, varyings2.lerp(&varyings3, ta)),
+                (bx, varyings1.lerp(&varyings3, tb)),
+                image,
+                depth_buffer,
+                emission_buffer.as_deref_mut(),
+                shading,
+            );
         }
+    }
 
-        // These are integers as the number represents pixels, which cannot be floats
-        let dy1 = (y2 - y1) as i32;
-        let dx1 = (x2 - x1) as i32;
-
-        let dv1 = v2 - v1;
-        let du1 = u2 - u1;
-        let dw1 = w2 - w1;
-
-        // These are integers as the number represents pixels, which cannot be floats
-        let dy2 = (y3 - y1) as i32;
-        let dx2 = (x3 - x1) as i32;
-
-        let dv2 = v3 - v1;
-        let du2 = u3 - u1;
-        let dw2 = w3 - w1;
-
-        // Change in x for a unit change in y for A and B sides
-        let mut dax_step = 0.0;
-        let mut dbx_step = 0.0;
-
-        // Same as above but for u and vj
-        let mut du1_step = 0.0;
-        let mut du2_step = 0.0;
-
-        let mut dv1_step = 0.0;
-        let mut dv2_step = 0.0;
-
-        let mut dw1_step = 0.0;
-        let mut dw2_step = 0.0;
-
-        if dy1 != 0 {
-            dax_step = dx1 as f32 / dy1.abs() as f32;
-            du1_step = du1 as f32 / dy1.abs() as f32;
-            dv1_step = dv1 as f32 / dy1.abs() as f32;
-            dw1_step = dw1 as f32 / dy1.abs() as f32;
-        }
-        if dy2 != 0 {
-            dbx_step = dx2 as f32 / dy2.abs() as f32;
-            du2_step = du2 as f32 / dy2.abs() as f32;
-            dv2_step = dv2 as f32 / dy2.abs() as f32;
-            dw2_step = dw2 as f32 / dy2.abs() as f32;
+    #[allow(clippy::too_many_arguments)]
+    fn draw_scanline(
+        triangle: &Triangle,
+        y: i32,
+        start: (i32, Varyings),
+        end: (i32, Varyings),
+        image: &mut Image,
+        depth_buffer: &mut [f32],
+        mut emission_buffer: Option<&mut [[f32; 3]]>,
+        shading: &Shading,
+    ) {
+        // Ensure that start is left of end => Drawing from left to right
+        let ((ax, start), (bx, end)) = if start.0 > end.0 {
+            (end, start)
+        } else {
+            (start, end)
+        };
+
+        if y >= image.height() as i32 {
+            return;
         }
 
-        // First half of the triangle if it is not flat
-        if dy1 != 0 {
-            // For every scanline between y1 and y2
-            for i in y1 as i32..y2 as i32 {
-                // Ax and Bx are the starting and ending x values in a scanline repectively
-                let mut ax = (x1 as f32 + dax_step * (i - y1) as f32) as i32;
-                let mut bx = (x1 as f32 + dbx_step * (i - y1) as f32) as i32;
-
-                // Same but for starting texture coordinates
-                let mut tex_su = u1 + du1_step * (i - y1) as f32;
-                let mut tex_sv = v1 + dv1_step * (i - y1) as f32;
-                let mut tex_sw = w1 + dw1_step * (i - y1) as f32;
-                // Same but for ending texture coordinates
-                let mut tex_eu = u1 + du2_step * (i - y1) as f32;
-                let mut tex_ev = v1 + dv2_step * (i - y1) as f32;
-                let mut tex_ew = w1 + dw2_step * (i - y1) as f32;
-
-                // Ensure that ax < bx => Drawing from left to right
-                if ax > bx {
-                    swap(&mut ax, &mut bx);
-                    swap(&mut tex_su, &mut tex_eu);
-                    swap(&mut tex_sv, &mut tex_ev);
-                    swap(&mut tex_sw, &mut tex_ew);
-                }
-
-                // t represents the normalized position between ax and bx => Where we are in the scanline
-                let t_step = 1.0 / (bx - ax) as f32;
-                let mut t = 0.0;
+        // t represents the normalized position between ax and bx => Where we are in the scanline
+        let t_step = 1.0 / (bx - ax) as f32;
+        let mut t = 0.0;
 
-                for j in ax..=bx {
-                    let tex_u = (1.0 - t) * tex_su + t * tex_eu;
-                    let tex_v = (1.0 - t) * tex_sv + t * tex_ev;
-                    let tex_w = (1.0 - t) * tex_sw + t * tex_ew;
+        for x in ax..=bx.min(image.width() as i32 - 1) {
+            let varyings = start.lerp(&end, t);
+            t += t_step;
 
-                    let color = if let Some(texture) = texture {
-                        let tex_x = ((tex_u / tex_w) * texture.width() as f32) as u32;
-                        let tex_y = ((tex_v / tex_w) * texture.height() as f32) as u32;
-
-                        let tex_x = tex_x.clamp(0, texture.width().saturating_sub(1) as u32);
-                        let tex_y = tex_y.clamp(0, texture.height().saturating_sub(1) as u32);
-
-                        let color = texture.get_pixel(tex_x, tex_y);
-
-                        color
-                    } else {
-                        let color_value = triangle.intensity as u8;
-                        let color = Color::from_rgba(color_value, color_value, color_value, 255);
-                        color
-                    };
+            // Depth test -> Projected depth gets larger closer to the camera
+            let index = x as usize + y as usize * image.width();
+            if varyings.z <= depth_buffer[index] {
+                continue;
+            }
 
-                    if j < image.width() as i32 && i < image.height() as i32 {
-                        // Update depth buffer
-                        let pixel_depth =
-                            depth_buffer[j as usize + i as usize * image.width() as usize];
+            let (mut color, emission) = Self::shade_pixel(triangle, &varyings, shading);
 
-                        if tex_w < pixel_depth {
-                            image.set_pixel(j as u32, i as u32, color);
-                            depth_buffer[j as usize + i as usize * image.width() as usize] = tex_w;
-                        }
+            match shading.material.alpha_mode {
+                AlphaMode::Opaque => {}
+                AlphaMode::Mask(cutoff) => {
+                    if color.a < cutoff {
+                        continue;
                     }
-
-                    t += t_step;
+                }
+                AlphaMode::Blend => {
+                    // Blend over what is already there and keep the depth of whatever is behind
+                    let background = image.get_pixel(x as u32, y as u32).to_vec();
+                    let mut blended = Color::from_vec(background.lerp(color.to_vec(), color.a));
+                    blended.a = 1.0;
+                    image.set_pixel(x as u32, y as u32, blended);
+                    if let Some(emission_buffer) = emission_buffer.as_deref_mut() {
+                        let behind = emission_buffer[index];
+                        emission_buffer[index] =
+                            [0, 1, 2].map(|c| behind[c] + (emission[c] - behind[c]) * color.a);
+                    }
+                    continue;
                 }
             }
-        }
-
-        // Resetting values for the second half of the triangle
-        let dy1 = y3 - y2;
-        let dx1 = x3 - x2;
-
-        let dv1 = v3 - v2;
-        let du1 = u3 - u2;
-        let dw1 = w3 - w2;
 
-        du1_step = 0.0;
-        dv1_step = 0.0;
-        dw1_step = 0.0;
-
-        if dy1 != 0 {
-            dax_step = dx1 as f32 / dy1.abs() as f32;
-            du1_step = du1 as f32 / dy1.abs() as f32;
-            dv1_step = dv1 as f32 / dy1.abs() as f32;
-            dw1_step = dw1 as f32 / dy1.abs() as f32;
-        }
-        if dy2 != 0 {
-            dbx_step = dx2 as f32 / dy2.abs() as f32;
+            color.a = 1.0;
+            image.set_pixel(x as u32, y as u32, color);
+            depth_buffer[index] = varyings.z;
+            if let Some(emission_buffer) = emission_buffer.as_deref_mut() {
+                emission_buffer[index] = emission;
+            }
         }
+    }
 
-        if dy1 != 0 {
-            for i in y2 as i32..y3 as i32 {
-                // Ax and Bx are the starting and ending x values in a scanline repectively
-                let mut ax = (x2 as f32 + dax_step * (i - y2) as f32) as i32;
-                let mut bx = (x1 as f32 + dbx_step * (i - y1) as f32) as i32;
-
-                // Same but for starting texture coordinates
-                let mut tex_su = u2 + du1_step * (i - y2) as f32;
-                let mut tex_sv = v2 + dv1_step * (i - y2) as f32;
-                let mut tex_sw = w2 + dw1_step * (i - y2) as f32;
-
-                // Same but for ending texture coordinates
-                let mut tex_eu = u1 + du2_step * (i - y1) as f32;
-                let mut tex_ev = v1 + dv2_step * (i - y1) as f32;
-                let mut tex_ew = w1 + dw2_step * (i - y1) as f32;
-
-                // Ensure that ax < bx => Drawing from left to right
-                if ax > bx {
-                    swap(&mut ax, &mut bx);
-                    swap(&mut tex_su, &mut tex_eu);
-                    swap(&mut tex_sv, &mut tex_ev);
-                    swap(&mut tex_sw, &mut tex_ew);
-                }
-
-                // t represents the normalized position between ax and bx => Where we are in the scanline
-                let t_step = 1.0 / (bx - ax) as f32;
-                let mut t = 0.0;
-
-                for j in ax..=bx {
-                    let tex_u = (1.0 - t) * tex_su + t * tex_eu;
-                    let tex_v = (1.0 - t) * tex_sv + t * tex_ev;
-                    let tex_w = (1.0 - t) * tex_sw + t * tex_ew;
-
-                    let color = if let Some(texture) = texture {
-                        let tex_x = ((tex_u / tex_w) * texture.width() as f32) as u32;
-                        let tex_y = ((tex_v / tex_w) * texture.height() as f32) as u32;
-
-                        let tex_x = tex_x.clamp(0, texture.width().saturating_sub(1) as u32);
-                        let tex_y = tex_y.clamp(0, texture.height().saturating_sub(1) as u32);
-
-                        let color = texture.get_pixel(tex_x, tex_y);
-                        color
-                    } else {
-                        let color_value = triangle.intensity as u8;
-                        let color = Color::from_rgba(color_value, color_value, color_value, 255);
-                        color
-                    };
-
-                    if j < image.width() as i32 && i < image.height() as i32 {
-                        // Update depth buffer
-                        let pixel_depth =
-                            depth_buffer[j as usize + i as usize * image.width() as usize];
-
-                        if tex_w < pixel_depth {
-                            image.set_pixel(j as u32, i as u32, color);
-                            depth_buffer[j as usize + i as usize * image.width() as usize] = tex_w;
-                        }
-                    }
+    // Colour of the pixel, and how much of it is emitted light after fog
+    fn shade_pixel(
+        triangle: &Triangle,
+        varyings: &Varyings,
+        shading: &Shading,
+    ) -> (Color, [f32; 3]) {
+        let vertex_color = varyings.color / varyings.w;
+
+        let tex_u = varyings.u / varyings.w;
+        let tex_v = varyings.v / varyings.w;
+
+        let mut color = if let Some(texture) = shading.texture {
+            Color::from_vec(sample_texture(texture, tex_u, tex_v).to_vec() * vertex_color)
+        } else {
+            // Only untextured faces are lit
+            let light = triangle.intensity.clamp(0.0, 255.0) / 255.0;
+            Color::from_vec(vertex_color * Vec4::new(light, light, light, 1.0))
+        };
+        let alpha = color.a * shading.material.opacity;
+
+        let world_position = vec_div(&varyings.world_position, varyings.w);
+
+        // Mirror the skybox along the view direction reflected off the surface
+        if let Some(skybox) = shading.skybox
+            && shading.material.reflectivity > 0.0
+        {
+            let view_direction = if shading.orthographic {
+                shading.view_forward
+            } else {
+                vec_sub(&world_position, shading.camera_position).normalize()
+            };
+            let reflection = skybox.sample(&reflect(&view_direction, &triangle.normal));
+
+            color = Color::from_vec(
+                color
+                    .to_vec()
+                    .lerp(reflection.to_vec(), shading.material.reflectivity),
+            );
+        }
 
-                    t += t_step;
-                }
+        // Emission ignores lighting and reflections and is simply added on top
+        let mut emission = [0.0; 3];
+        if shading.material.is_emissive() {
+            let mut emissive = shading.material.emissive.to_vec();
+            if let Some(emissive_texture) = &shading.material.emissive_texture {
+                emissive *= sample_texture(emissive_texture, tex_u, tex_v).to_vec();
             }
+            color.r += emissive.x;
+            color.g += emissive.y;
+            color.b += emissive.z;
+            emission = [emissive.x, emissive.y, emissive.z];
         }
+
+        if let Some(fog) = shading.fog {
+            // View space depth -> Distance along the view direction, whatever the projection
+            let depth = dot_product(
+                &vec_sub(&world_position, shading.camera_position),
+                &shading.view_forward,
+            );
+            let amount = fog.amount(depth, shading.camera_position.y, world_position.y);
+            color = Color::from_vec(color.to_vec().lerp(fog.color.to_vec(), amount));
+            // Glow fades into the fog along with the rest of the surface
+            emission = emission.map(|c| c * (1.0 - amount));
+        }
+
+        color.a = alpha;
+        (color, emission)
     }
 
     fn _draw_triangle_face(triangle: Triangle) {
@@ -749,3 +1162,12 @@ impl Mesh {
         );
     }
 }
+
+// Given to faces that were read without texture coordinates
+pub(crate) fn placeholder_texture_coords() -> [Vector2; 3] {
+    [
+        Vector2::new(0.0, 0.0),
+        Vector2::new(1.0, 0.0),
+        Vector2::new(1.0, 1.0),
+    ]
+}
@@ -0,0 +1,202 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+};
+
+use crate::{
+    Camera, Vector3, invalid_data,
+    matrix::{Quaternion, quat_slerp, vec_add, vec_mul},
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Keyframe {
+    pub position: Vector3,
+    pub orientation: Quaternion,
+}
+
+// Recorded camera poses that can be played back as a smooth fly-through
+pub struct CameraPath {
+    pub keyframes: Vec<Keyframe>,
+    // Keyframes passed per second during playback
+    pub speed: f32,
+    // Start over at the first keyframe instead of stopping at the last one
+    pub looping: bool,
+    // Playback position -> Whole numbers are exactly on a keyframe
+    time: f32,
+    playing: bool,
+}
+
+impl Default for CameraPath {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CameraPath {
+    pub fn new() -> Self {
+        CameraPath {
+            keyframes: Vec::new(),
+            speed: 0.5,
+            looping: false,
+            time: 0.0,
+            playing: false,
+        }
+    }
+
+    // One keyframe per line: position x y z followed by orientation x y z w
+    pub fn load(path: &str) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let reader = BufReader::new(file);
+
+        let mut camera_path = CameraPath::new();
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let values = line
+                .split_whitespace()
+                .map(|value| value.parse::<f32>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| invalid_data(&format!("invalid keyframe: {line}")))?;
+            let [px, py, pz, qx, qy, qz, qw] = values[..] else {
+                return Err(invalid_data(&format!(
+                    "expected 7 values per keyframe: {line}"
+                )));
+            };
+
+            camera_path.keyframes.push(Keyframe {
+                position: Vector3::new(px, py, pz),
+                orientation: Quaternion {
+                    x: qx,
+                    y: qy,
+                    z: qz,
+                    w: qw,
+                }
+                .normalize(),
+            });
+        }
+
+        Ok(camera_path)
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "# position x y z, orientation x y z w")?;
+        for keyframe in &self.keyframes {
+            let (p, q) = (keyframe.position, keyframe.orientation);
+            writeln!(
+                writer,
+                "{} {} {} {} {} {} {}",
+                p.x, p.y, p.z, q.x, q.y, q.z, q.w
+            )?;
+        }
+        writer.flush()
+    }
+
+    pub fn record(&mut self, camera: &Camera) {
+        self.keyframes.push(Keyframe {
+            position: camera.position,
+            orientation: camera.orientation,
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.keyframes.clear();
+        self.stop();
+    }
+
+    // Starts from the first keyframe -> Needs at least two to go anywhere
+    pub fn play(&mut self) {
+        self.time = 0.0;
+        self.playing = self.keyframes.len() >= 2;
+    }
+
+    pub fn stop(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    // Moves the playback on by `delta` seconds and puts the camera there
+    pub fn update(&mut self, camera: &mut Camera, delta: f32) {
+        if !self.playing {
+            return;
+        }
+        // The keyframes are public, so they may have been cleared since play
+        let Some(last) = self.keyframes.len().checked_sub(1) else {
+            self.stop();
+            return;
+        };
+
+        let end = last as f32;
+        self.time += self.speed * delta;
+        if self.time >= end {
+            if self.looping && end > 0.0 {
+                self.time %= end;
+            } else {
+                self.time = end;
+                self.playing = false;
+            }
+        }
+
+        if let Some(keyframe) = self.sample(self.time) {
+            camera.position = keyframe.position;
+            camera.orientation = keyframe.orientation;
+        }
+    }
+
+    // Pose at a point along the path, from 0.0 at the first keyframe to len - 1 at the last.
+    // Positions follow a Catmull-Rom spline through the keyframes and orientations are slerped.
+    // None for a path without keyframes
+    pub fn sample(&self, time: f32) -> Option<Keyframe> {
+        let last = self.keyframes.len().checked_sub(1)?;
+        let time = time.clamp(0.0, last as f32);
+        let index = (time as usize).min(last.saturating_sub(1));
+        let t = time - index as f32;
+
+        // The ends are repeated so the curve still has a neighbour on both sides
+        let keyframe = |i: isize| self.keyframes[i.clamp(0, last as isize) as usize];
+        let i = index as isize;
+        let (k0, k1, k2, k3) = (
+            keyframe(i - 1),
+            keyframe(i),
+            keyframe(i + 1),
+            keyframe(i + 2),
+        );
+
+        Some(Keyframe {
+            position: catmull_rom(&k0.position, &k1.position, &k2.position, &k3.position, t),
+            orientation: quat_slerp(&k1.orientation, &k2.orientation, t),
+        })
+    }
+}
+
+// Passes through p1 at t = 0.0 and p2 at t = 1.0, with p0 and p3 shaping the tangents
+fn catmull_rom(p0: &Vector3, p1: &Vector3, p2: &Vector3, p3: &Vector3, t: f32) -> Vector3 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+
+    let a = vec_mul(p1, 2.0);
+    let b = vec_mul(&vec_add(&vec_mul(p0, -1.0), p2), t);
+    let c = vec_add(
+        &vec_add(&vec_mul(p0, 2.0), &vec_mul(p1, -5.0)),
+        &vec_add(&vec_mul(p2, 4.0), &vec_mul(p3, -1.0)),
+    );
+    let d = vec_add(
+        &vec_add(&vec_mul(p0, -1.0), &vec_mul(p1, 3.0)),
+        &vec_add(&vec_mul(p2, -3.0), p3),
+    );
+
+    vec_mul(
+        &vec_add(
+            &vec_add(&a, &b),
+            &vec_add(&vec_mul(&c, t2), &vec_mul(&d, t3)),
+        ),
+        0.5,
+    )
+}
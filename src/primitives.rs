@@ -0,0 +1,434 @@
+use std::f32::consts::{PI, TAU};
+
+use crate::{
+    Vector3,
+    matrix::Vector2,
+    mesh::{Face, Mesh, Vertex},
+};
+
+// Generated meshes, centred on the origin with y up. Faces wind so that
+// cross(v1 - v0, v2 - v0) points out of the shape, as with Mesh::cube, and texture coordinates
+// start at the top left of the image
+impl Mesh {
+    // Flat grid on the xz plane facing up, with the texture stretched over all of it
+    pub fn plane(width: f32, depth: f32, x_segments: usize, z_segments: usize) -> Self {
+        let (x_segments, z_segments) = (x_segments.max(1), z_segments.max(1));
+        let mut builder = Builder::default();
+
+        let mut index = |i: usize, j: usize| {
+            let (u, v) = (i as f32 / x_segments as f32, j as f32 / z_segments as f32);
+            builder.vertex(Vector3::new((u - 0.5) * width, 0.0, (v - 0.5) * depth))
+        };
+        let rows: Vec<Vec<usize>> = (0..=z_segments)
+            .map(|j| (0..=x_segments).map(|i| index(i, j)).collect())
+            .collect();
+
+        for j in 0..z_segments {
+            for i in 0..x_segments {
+                let uv = |i: usize, j: usize| {
+                    Vector2::new(i as f32 / x_segments as f32, j as f32 / z_segments as f32)
+                };
+                builder.quad([
+                    (rows[j][i], uv(i, j)),
+                    (rows[j + 1][i], uv(i, j + 1)),
+                    (rows[j + 1][i + 1], uv(i + 1, j + 1)),
+                    (rows[j][i + 1], uv(i + 1, j)),
+                ]);
+            }
+        }
+        builder.build()
+    }
+
+    // Rings of latitude and segments of longitude, with the texture wrapped around it like a
+    // globe -> Eg: an equirectangular image
+    pub fn uv_sphere(radius: f32, segments: usize, rings: usize) -> Self {
+        let rings = rings.max(2);
+        let profile: Vec<_> = (0..=rings)
+            .map(|j| {
+                let v = j as f32 / rings as f32;
+                let angle = v * PI;
+                (radius * angle.sin(), radius * angle.cos(), v)
+            })
+            .collect();
+
+        let mut builder = Builder::default();
+        builder.revolve(&profile, segments, false, |u, v, _| Vector2::new(u, v));
+        builder.build()
+    }
+
+    // Icosahedron with each face split into four `subdivisions` times -> Evenly sized faces, unlike
+    // a UV sphere. Texture coordinates are mapped the same way as for Mesh::uv_sphere
+    pub fn icosphere(radius: f32, subdivisions: usize) -> Self {
+        let t = (1.0 + 5.0_f32.sqrt()) / 2.0;
+        let mut positions = vec![
+            Vector3::new(-1.0, t, 0.0),
+            Vector3::new(1.0, t, 0.0),
+            Vector3::new(-1.0, -t, 0.0),
+            Vector3::new(1.0, -t, 0.0),
+            Vector3::new(0.0, -1.0, t),
+            Vector3::new(0.0, 1.0, t),
+            Vector3::new(0.0, -1.0, -t),
+            Vector3::new(0.0, 1.0, -t),
+            Vector3::new(t, 0.0, -1.0),
+            Vector3::new(t, 0.0, 1.0),
+            Vector3::new(-t, 0.0, -1.0),
+            Vector3::new(-t, 0.0, 1.0),
+        ];
+        let mut triangles: Vec<[usize; 3]> = vec![
+            [0, 11, 5],
+            [0, 5, 1],
+            [0, 1, 7],
+            [0, 7, 10],
+            [0, 10, 11],
+            [1, 5, 9],
+            [5, 11, 4],
+            [11, 10, 2],
+            [10, 7, 6],
+            [7, 1, 8],
+            [3, 9, 4],
+            [3, 4, 2],
+            [3, 2, 6],
+            [3, 6, 8],
+            [3, 8, 9],
+            [4, 9, 5],
+            [2, 4, 11],
+            [6, 2, 10],
+            [8, 6, 7],
+            [9, 8, 1],
+        ];
+
+        for _ in 0..subdivisions {
+            // Edges shared by two faces get a single new vertex in the middle
+            let mut midpoints = std::collections::HashMap::new();
+            let mut midpoint = |a: usize, b: usize| {
+                *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                    let (pa, pb) = (positions[a], positions[b]);
+                    positions.push(Vector3::new(
+                        (pa.x + pb.x) / 2.0,
+                        (pa.y + pb.y) / 2.0,
+                        (pa.z + pb.z) / 2.0,
+                    ));
+                    positions.len() - 1
+                })
+            };
+
+            triangles = triangles
+                .into_iter()
+                .flat_map(|[a, b, c]| {
+                    let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                    [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+                })
+                .collect();
+        }
+
+        let vertices: Vec<Vertex> = positions
+            .iter()
+            .map(|p| {
+                let scale = radius / (p.x * p.x + p.y * p.y + p.z * p.z).sqrt();
+                Vector3::new(p.x * scale, p.y * scale, p.z * scale)
+            })
+            .collect();
+
+        // The seam is where u wraps from 1 back to 0, on the half of the z = 0 plane with x > 0.
+        // Faces crossing it are cut in two along it, so neither half stretches over the whole
+        // texture. Cuts are shared by the two faces on either side of an edge
+        let mut builder = Builder {
+            vertices,
+            faces: Vec::new(),
+        };
+        let mut cuts = std::collections::HashMap::new();
+        for corners in triangles {
+            let positions = corners.map(|v| builder.vertices[v]);
+            let crosses_seam = (0..3).any(|i| {
+                let (p, q) = (positions[i], positions[(i + 1) % 3]);
+                p.z * q.z < 0.0 && p.x + (q.x - p.x) * p.z / (p.z - q.z) > 0.0
+            });
+
+            if !crosses_seam {
+                // Past the seam means z > 0, where u is close to 1
+                let past_seam = positions.iter().any(|p| p.z > 0.0);
+                builder.sphere_triangle(corners, past_seam);
+                continue;
+            }
+
+            for past_seam in [false, true] {
+                // The part of the face on one side of the seam, in the same winding order
+                let mut polygon = Vec::with_capacity(4);
+                for i in 0..3 {
+                    let (a, b) = (corners[i], corners[(i + 1) % 3]);
+                    let (pa, pb) = (positions[i], positions[(i + 1) % 3]);
+                    let keep = |z: f32| if past_seam { z >= 0.0 } else { z <= 0.0 };
+
+                    if keep(pa.z) {
+                        polygon.push(a);
+                    }
+                    if pa.z * pb.z < 0.0 {
+                        let cut = *cuts.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                            let t = pa.z / (pa.z - pb.z);
+                            builder.vertex(Vector3::new(
+                                pa.x + (pb.x - pa.x) * t,
+                                pa.y + (pb.y - pa.y) * t,
+                                0.0,
+                            ))
+                        });
+                        polygon.push(cut);
+                    }
+                }
+
+                for i in 1..polygon.len().saturating_sub(1) {
+                    builder.sphere_triangle([polygon[0], polygon[i], polygon[i + 1]], past_seam);
+                }
+            }
+        }
+
+        builder.build()
+    }
+
+    // Closed tube along y. The texture wraps around the side and each cap gets its own circle of
+    // it
+    pub fn cylinder(radius: f32, height: f32, segments: usize, height_segments: usize) -> Self {
+        let height_segments = height_segments.max(1);
+        let side: Vec<_> = (0..=height_segments)
+            .map(|j| {
+                let v = j as f32 / height_segments as f32;
+                (radius, height * (0.5 - v), v)
+            })
+            .collect();
+
+        let mut builder = Builder::default();
+        let rings = builder.revolve(&side, segments, false, |u, v, _| Vector2::new(u, v));
+        builder.cap(&rings[0], true);
+        builder.cap(&rings[height_segments], false);
+        builder.build()
+    }
+
+    // Point at the top and a flat base at the bottom
+    pub fn cone(radius: f32, height: f32, segments: usize, height_segments: usize) -> Self {
+        let height_segments = height_segments.max(1);
+        let side: Vec<_> = (0..=height_segments)
+            .map(|j| {
+                let v = j as f32 / height_segments as f32;
+                (radius * v, height * (0.5 - v), v)
+            })
+            .collect();
+
+        let mut builder = Builder::default();
+        let rings = builder.revolve(&side, segments, false, |u, v, _| Vector2::new(u, v));
+        builder.cap(&rings[height_segments], false);
+        builder.build()
+    }
+
+    // Ring lying on the xz plane. `radius` is to the middle of the tube. The texture wraps around
+    // the ring along u and around the tube along v
+    pub fn torus(radius: f32, tube_radius: f32, segments: usize, tube_segments: usize) -> Self {
+        let tube_segments = tube_segments.max(3);
+        // Starting on the outside and heading down first -> Faces wind outwards
+        let profile: Vec<_> = (0..=tube_segments)
+            .map(|j| {
+                let v = j as f32 / tube_segments as f32;
+                let angle = v * TAU;
+                (
+                    radius + tube_radius * angle.cos(),
+                    -tube_radius * angle.sin(),
+                    v,
+                )
+            })
+            .collect();
+
+        let mut builder = Builder::default();
+        builder.revolve(&profile, segments, true, |u, v, _| Vector2::new(u, v));
+        builder.build()
+    }
+
+    // Cylinder with a half sphere on each end, `height` tall from tip to tip. `rings` is per
+    // half sphere. The texture wraps around it, spread evenly along its length
+    pub fn capsule(radius: f32, height: f32, segments: usize, rings: usize) -> Self {
+        let rings = rings.max(1);
+        let middle = (height - 2.0 * radius).max(0.0);
+
+        // Top half sphere from its tip down to its widest ring
+        let mut profile = Vec::with_capacity(2 * rings + 2);
+        for j in 0..=rings {
+            let angle = j as f32 / rings as f32 * PI / 2.0;
+            profile.push((radius * angle.sin(), middle / 2.0 + radius * angle.cos()));
+        }
+        // Then the bottom one -> The straight part is the quads between them. Without one, their
+        // widest rings are the same ring
+        let first = if middle > 0.0 { 0 } else { 1 };
+        for j in first..=rings {
+            let angle = PI / 2.0 + j as f32 / rings as f32 * PI / 2.0;
+            profile.push((radius * angle.sin(), -middle / 2.0 + radius * angle.cos()));
+        }
+
+        // v goes along the outline, so the texture isn't squashed on either part
+        let mut length = 0.0;
+        let mut lengths = vec![0.0];
+        for pair in profile.windows(2) {
+            let ((r1, y1), (r2, y2)) = (pair[0], pair[1]);
+            length += ((r2 - r1).powi(2) + (y2 - y1).powi(2)).sqrt();
+            lengths.push(length);
+        }
+        let profile: Vec<_> = profile
+            .iter()
+            .zip(lengths)
+            .map(|(&(r, y), l)| (r, y, l / length))
+            .collect();
+
+        let mut builder = Builder::default();
+        builder.revolve(&profile, segments, false, |u, v, _| Vector2::new(u, v));
+        builder.build()
+    }
+}
+
+// Where a point on a sphere lands on a texture wrapped around it like a globe
+fn sphere_texture_coords(position: &Vector3, radius: f32) -> Vector2 {
+    let longitude = (-position.z).atan2(position.x).rem_euclid(TAU);
+    let latitude = (position.y / radius).clamp(-1.0, 1.0).acos();
+    Vector2::new(longitude / TAU, latitude / PI)
+}
+
+#[derive(Default)]
+struct Builder {
+    vertices: Vec<Vertex>,
+    faces: Vec<Face>,
+}
+
+impl Builder {
+    fn vertex(&mut self, position: Vector3) -> usize {
+        self.vertices.push(position);
+        self.vertices.len() - 1
+    }
+
+    // Skips triangles that shrank to nothing, eg: next to a pole
+    fn triangle(&mut self, [a, b, c]: [(usize, Vector2); 3]) {
+        if a.0 == b.0 || b.0 == c.0 || c.0 == a.0 {
+            return;
+        }
+        self.faces.push(Face {
+            vertices: [a.0, b.0, c.0],
+            texture_coords: [a.1, b.1, c.1],
+        });
+    }
+
+    // Two triangles with their corners in this order
+    fn quad(&mut self, [a, b, c, d]: [(usize, Vector2); 4]) {
+        self.triangle([a, b, c]);
+        self.triangle([a, c, d]);
+    }
+
+    // Spins an outline around the y axis in `segments` steps. The outline is given as
+    // (distance from the axis, height, v) from top to bottom on the outside, which is what makes
+    // the faces wind outwards. Points on the axis become a single vertex. `closed` joins the last
+    // point back to the first, which it should be a copy of. Returns the vertices of each ring
+    fn revolve(
+        &mut self,
+        profile: &[(f32, f32, f32)],
+        segments: usize,
+        closed: bool,
+        uv: impl Fn(f32, f32, &Vector3) -> Vector2,
+    ) -> Vec<Vec<usize>> {
+        let segments = segments.max(3);
+        let on_axis: Vec<bool> = profile.iter().map(|&(r, _, _)| r.abs() < 1e-6).collect();
+
+        // A closed outline ends where it started -> Reuse the first ring rather than add it twice
+        let ring_count = profile.len() - usize::from(closed);
+        let mut rows: Vec<Vec<usize>> = profile[..ring_count]
+            .iter()
+            .zip(&on_axis)
+            .map(|(&(r, y, _), &on_axis)| {
+                if on_axis {
+                    vec![self.vertex(Vector3::new(0.0, y, 0.0)); segments]
+                } else {
+                    (0..segments)
+                        .map(|i| {
+                            let angle = i as f32 / segments as f32 * TAU;
+                            self.vertex(Vector3::new(r * angle.cos(), y, -r * angle.sin()))
+                        })
+                        .collect()
+                }
+            })
+            .collect();
+        if closed {
+            rows.push(rows[0].clone());
+        }
+
+        for j in 0..profile.len().saturating_sub(1) {
+            for i in 0..segments {
+                // Corners on the axis sit in the middle of their segment of the texture
+                let corner = |column: usize, row: usize| {
+                    let index = rows[row][column % segments];
+                    let u = if on_axis[row] {
+                        (i as f32 + 0.5) / segments as f32
+                    } else {
+                        column as f32 / segments as f32
+                    };
+                    (index, uv(u, profile[row].2, &self.vertices[index]))
+                };
+                let corners = [
+                    corner(i, j),
+                    corner(i, j + 1),
+                    corner(i + 1, j + 1),
+                    corner(i + 1, j),
+                ];
+                self.quad(corners);
+            }
+        }
+        rows
+    }
+
+    // Flat disc closing off a ring from Builder::revolve, facing up or down, with a circle of the
+    // texture on it
+    fn cap(&mut self, ring: &[usize], up: bool) {
+        let edge = self.vertices[ring[0]];
+        let radius = edge.x.hypot(edge.z);
+        let center = self.vertex(Vector3::new(0.0, edge.y, 0.0));
+        let corner = |index: usize, position: &Vector3| {
+            let uv = Vector2::new(
+                0.5 + position.x / (2.0 * radius),
+                0.5 + position.z / (2.0 * radius),
+            );
+            (index, uv)
+        };
+
+        for i in 0..ring.len() {
+            let middle = corner(center, &self.vertices[center]);
+            let a = corner(ring[i], &self.vertices[ring[i]]);
+            let b = corner(
+                ring[(i + 1) % ring.len()],
+                &self.vertices[ring[(i + 1) % ring.len()]],
+            );
+            // Rings turn from +x towards -z, which is anticlockwise seen from above
+            self.triangle(if up { [middle, a, b] } else { [middle, b, a] });
+        }
+    }
+
+    // Triangle on a sphere around the origin, with the texture wrapped around it like a globe.
+    // Corners on the seam take u = 1 when the rest of the triangle is `past_seam`, otherwise 0,
+    // and corners on a pole take the u in between the other two
+    fn sphere_triangle(&mut self, corners: [usize; 3], past_seam: bool) {
+        let positions = corners.map(|v| self.vertices[v]);
+        let on_pole = positions.map(|p| p.x == 0.0 && p.z == 0.0);
+        let mut coords = positions.map(|p| {
+            let radius = (p.x * p.x + p.y * p.y + p.z * p.z).sqrt();
+            let mut coords = sphere_texture_coords(&p, radius);
+            if p.z == 0.0 && p.x > 0.0 {
+                coords.u = if past_seam { 1.0 } else { 0.0 };
+            }
+            coords
+        });
+
+        for i in (0..3).filter(|&i| on_pole[i]) {
+            let others: Vec<f32> = (0..3)
+                .filter(|&j| !on_pole[j])
+                .map(|j| coords[j].u)
+                .collect();
+            coords[i].u = others.iter().sum::<f32>() / others.len().max(1) as f32;
+        }
+
+        self.triangle([0, 1, 2].map(|i| (corners[i], coords[i])));
+    }
+
+    fn build(self) -> Mesh {
+        Mesh::new(self.vertices, self.faces, None)
+    }
+}
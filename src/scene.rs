@@ -0,0 +1,182 @@
+use crate::{
+    Camera, Object, Vector3,
+    matrix::{Mat4x4, inverse_mat, mat_multiply, mult_vec_mat},
+};
+
+// Handle to an object in a Scene
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+struct Node {
+    object: Object,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    // Object space -> World space, as of the last update_transforms
+    world_mat: Mat4x4,
+}
+
+// Objects arranged in a tree -> Each object's position and rotation are relative to its parent,
+// so moving a car moves its wheels along with it
+#[derive(Default)]
+pub struct Scene {
+    // Parents always come before their children
+    nodes: Vec<Node>,
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Adds an object at the top of the tree, placed in world space
+    pub fn add(&mut self, object: Object) -> NodeId {
+        self.push(object, None)
+    }
+
+    // Adds an object placed relative to `parent`
+    pub fn add_child(&mut self, parent: NodeId, object: Object) -> NodeId {
+        let id = self.push(object, Some(parent));
+        self.nodes[parent.0].children.push(id);
+        id
+    }
+
+    fn push(&mut self, object: Object, parent: Option<NodeId>) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(Node {
+            object,
+            parent,
+            children: Vec::new(),
+            world_mat: [[0.0; 4]; 4],
+        });
+        self.update_transform(id);
+        id
+    }
+
+    pub fn object(&self, id: NodeId) -> &Object {
+        &self.nodes[id.0].object
+    }
+
+    // Call update_transforms after moving things for the change to show up in world space
+    pub fn object_mut(&mut self, id: NodeId) -> &mut Object {
+        &mut self.nodes[id.0].object
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].parent
+    }
+
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        &self.nodes[id.0].children
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    // Recomposes every world matrix from the local transforms, from the top of the tree down
+    pub fn update_transforms(&mut self) {
+        for i in 0..self.nodes.len() {
+            self.update_transform(NodeId(i));
+        }
+    }
+
+    // Picks each object's level of detail for the view from `camera`. Call after update_transforms
+    pub fn update_lods(&mut self, camera: &Camera) {
+        for node in &mut self.nodes {
+            if let Some(lod) = &mut node.object.lod {
+                lod.update(&node.object.mesh, &node.world_mat, camera);
+            }
+        }
+    }
+
+    fn update_transform(&mut self, id: NodeId) {
+        let node = &self.nodes[id.0];
+        let local_mat = node.object.local_matrix();
+        // Row vectors -> The local transform applies first, then the parent's
+        self.nodes[id.0].world_mat = match node.parent {
+            Some(parent) => mat_multiply(&local_mat, &self.nodes[parent.0].world_mat),
+            None => local_mat,
+        };
+    }
+
+    pub fn world_matrix(&self, id: NodeId) -> &Mat4x4 {
+        &self.nodes[id.0].world_mat
+    }
+
+    pub fn world_position(&self, id: NodeId) -> Vector3 {
+        let world_mat = self.world_matrix(id);
+        Vector3::new(world_mat[3][0], world_mat[3][1], world_mat[3][2])
+    }
+
+    // Axis aligned box around the object's mesh once it is placed in the world
+    pub fn world_bounding_box(&self, id: NodeId) -> (Vector3, Vector3) {
+        let (min, max) = self.object(id).mesh.bounding_box();
+        let world_mat = self.world_matrix(id);
+
+        let mut world_min = Vector3::new(f32::MAX, f32::MAX, f32::MAX);
+        let mut world_max = Vector3::new(f32::MIN, f32::MIN, f32::MIN);
+        for i in 0..8 {
+            let corner = Vector3::new(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+            );
+            let corner = mult_vec_mat(&corner, world_mat);
+            world_min = Vector3::new(
+                world_min.x.min(corner.x),
+                world_min.y.min(corner.y),
+                world_min.z.min(corner.z),
+            );
+            world_max = Vector3::new(
+                world_max.x.max(corner.x),
+                world_max.y.max(corner.y),
+                world_max.z.max(corner.z),
+            );
+        }
+        (world_min, world_max)
+    }
+
+    // Every object along with its world matrix, parents before children
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, &Object, &Mat4x4)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (NodeId(i), &node.object, &node.world_mat))
+    }
+
+    // Call update_transforms afterwards for changes to show up in world space
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (NodeId, &mut Object)> {
+        self.nodes
+            .iter_mut()
+            .enumerate()
+            .map(|(i, node)| (NodeId(i), &mut node.object))
+    }
+
+    // First object hit by a world space ray -> Distance along `direction`, the object and its face
+    pub fn intersect_ray(
+        &self,
+        origin: &Vector3,
+        direction: &Vector3,
+    ) -> Option<(f32, NodeId, usize)> {
+        self.iter()
+            .filter_map(|(id, object, world_mat)| {
+                let inverse = inverse_mat(world_mat);
+                let origin = mult_vec_mat(origin, &inverse);
+                // No translation for directions
+                let direction = mult_vec_mat(
+                    &Vector3 {
+                        w: 0.0,
+                        ..*direction
+                    },
+                    &inverse,
+                );
+                let (t, face) = object.mesh.intersect_ray(&origin, &direction)?;
+                Some((t, id, face))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+    }
+}
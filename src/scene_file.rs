@@ -0,0 +1,591 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use macroquad::{
+    color::{Color, WHITE},
+    texture::Image,
+};
+
+use crate::{
+    AlphaMode, Assets, Bloom, Camera, Fog, FogMode, Lod, LodLevel, LodMetric, Material, Mesh,
+    NodeId, Object, Scene, Skybox, Vector3, invalid_data, json::Json,
+};
+
+// Everything needed to view a scene, read from a JSON file. Paths in the file are relative to it.
+// Angles are in degrees and colours are "#rrggbb" strings. See the README for the full format
+pub struct SceneFile {
+    pub scene: Scene,
+    // Where the camera starts
+    pub camera: Camera,
+    pub light_direction: Vector3,
+    pub skybox: Option<Skybox>,
+    pub fog: Option<Fog>,
+    pub bloom: Option<Bloom>,
+}
+
+impl SceneFile {
+    // Meshes, textures and materials come from `assets`, so files used more than once are shared
+    pub fn load(path: &str, assets: &mut Assets) -> io::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        let root = Json::parse(&contents)
+            .map_err(|message| invalid_data(&format!("{path}: {message}")))?;
+
+        let mut loader = Loader {
+            file: path,
+            assets,
+            directory: Path::new(path)
+                .parent()
+                .unwrap_or(Path::new(""))
+                .to_path_buf(),
+            default_material: Rc::new(Material::default()),
+        };
+        loader.scene_file(&root)
+    }
+}
+
+// Nothing to look at, lit the same way as a file without lights
+impl Default for SceneFile {
+    fn default() -> Self {
+        SceneFile {
+            scene: Scene::new(),
+            camera: Camera::new(),
+            light_direction: Vector3::new(0.0, 0.0, -1.0),
+            skybox: None,
+            fog: None,
+            bloom: None,
+        }
+    }
+}
+
+// Turns the parsed JSON into engine types, with errors that say where in the file they are
+struct Loader<'a> {
+    file: &'a str,
+    assets: &'a mut Assets,
+    directory: PathBuf,
+    // Shared by every object without a material, so they can be drawn together
+    default_material: Rc<Material>,
+}
+
+impl Loader<'_> {
+    fn error(&self, at: &str, message: &str) -> io::Error {
+        invalid_data(&format!("{}: {at}: {message}", self.file))
+    }
+
+    fn path(&self, relative: &str) -> PathBuf {
+        self.directory.join(relative)
+    }
+
+    // Unknown keys are most likely typos, so they are errors rather than silently ignored
+    fn check_keys(&self, value: &Json, at: &str, keys: &[&str]) -> io::Result<()> {
+        let fields = value
+            .as_object()
+            .ok_or_else(|| self.error(at, "expected an object"))?;
+        match fields.iter().find(|(key, _)| !keys.contains(&key.as_str())) {
+            Some((key, _)) => Err(self.error(at, &format!("unknown key {key}"))),
+            None => Ok(()),
+        }
+    }
+
+    fn number(&self, value: &Json, at: &str) -> io::Result<f32> {
+        value
+            .as_f32()
+            .ok_or_else(|| self.error(at, "expected a number"))
+    }
+
+    fn string<'j>(&self, value: &'j Json, at: &str) -> io::Result<&'j str> {
+        value
+            .as_str()
+            .ok_or_else(|| self.error(at, "expected a string"))
+    }
+
+    fn boolean(&self, value: &Json, at: &str) -> io::Result<bool> {
+        value
+            .as_bool()
+            .ok_or_else(|| self.error(at, "expected true or false"))
+    }
+
+    // Eg: [1.0, 2.0, 3.0]
+    fn vector(&self, value: &Json, at: &str) -> io::Result<Vector3> {
+        match value.as_array() {
+            Some([x, y, z]) => Ok(Vector3::new(
+                self.number(x, at)?,
+                self.number(y, at)?,
+                self.number(z, at)?,
+            )),
+            _ => Err(self.error(at, "expected [x, y, z]")),
+        }
+    }
+
+    // Eg: "#bed7eb"
+    fn color(&self, value: &Json, at: &str) -> io::Result<Color> {
+        let text = self.string(value, at)?;
+        let hex = text
+            .strip_prefix('#')
+            .filter(|hex| hex.len() == 6)
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .ok_or_else(|| {
+                self.error(
+                    at,
+                    &format!("expected a colour like \"#rrggbb\", got {text}"),
+                )
+            })?;
+        Ok(Color::from_rgba(
+            (hex >> 16) as u8,
+            (hex >> 8) as u8,
+            hex as u8,
+            255,
+        ))
+    }
+
+    fn optional<'j, T>(
+        &self,
+        value: &'j Json,
+        key: &str,
+        at: &str,
+        read: impl FnOnce(&'j Json, &str) -> io::Result<T>,
+    ) -> io::Result<Option<T>> {
+        match value.get(key) {
+            Some(Json::Null) | None => Ok(None),
+            Some(field) => read(field, &format!("{at}.{key}")).map(Some),
+        }
+    }
+
+    fn scene_file(&mut self, root: &Json) -> io::Result<SceneFile> {
+        self.check_keys(root, "file", &["camera", "lights", "render", "objects"])?;
+
+        let mut scene = Scene::new();
+        if let Some(objects) = root.get("objects") {
+            let objects = objects
+                .as_array()
+                .ok_or_else(|| self.error("objects", "expected an array"))?;
+            for (i, object) in objects.iter().enumerate() {
+                self.object(object, &format!("objects[{i}]"), &mut scene, None)?;
+            }
+        }
+
+        let camera = match root.get("camera") {
+            Some(camera) => self.camera(camera)?,
+            None => Camera::new(),
+        };
+
+        // The renderer has a single directional light
+        let light_direction = match root.get("lights").map(|lights| lights.as_array()) {
+            None => Vector3::new(0.0, 0.0, -1.0),
+            Some(Some([light])) => {
+                self.check_keys(light, "lights[0]", &["direction"])?;
+                let direction = light
+                    .get("direction")
+                    .ok_or_else(|| self.error("lights[0]", "missing direction"))?;
+                self.vector(direction, "lights[0].direction")?.normalize()
+            }
+            Some(_) => return Err(self.error("lights", "expected an array with one light")),
+        };
+
+        let (skybox, fog, bloom) = match root.get("render") {
+            Some(render) => {
+                self.check_keys(render, "render", &["skybox", "fog", "bloom"])?;
+                (
+                    match render.get("skybox") {
+                        Some(Json::Null) | None => None,
+                        Some(skybox) => Some(self.skybox(skybox, "render.skybox")?),
+                    },
+                    self.optional(render, "fog", "render", |v, at| self.fog(v, at))?,
+                    self.optional(render, "bloom", "render", |v, at| self.bloom(v, at))?,
+                )
+            }
+            None => (None, None, None),
+        };
+
+        Ok(SceneFile {
+            scene,
+            camera,
+            light_direction,
+            skybox,
+            fog,
+            bloom,
+        })
+    }
+
+    fn camera(&self, value: &Json) -> io::Result<Camera> {
+        let at = "camera";
+        self.check_keys(
+            value,
+            at,
+            &[
+                "position",
+                "yaw",
+                "pitch",
+                "roll",
+                "fov",
+                "near",
+                "far",
+                "orthographic",
+                "ortho_height",
+            ],
+        )?;
+
+        let mut camera = Camera::new();
+        let number = |key| self.optional(value, key, at, |v, at| self.number(v, at));
+        if let Some(position) = self.optional(value, "position", at, |v, at| self.vector(v, at))? {
+            camera.position = position;
+        }
+        camera.set_rotation(
+            number("yaw")?.unwrap_or(0.0).to_radians(),
+            number("pitch")?.unwrap_or(0.0).to_radians(),
+            number("roll")?.unwrap_or(0.0).to_radians(),
+        );
+        camera.fov = number("fov")?.unwrap_or(camera.fov);
+        camera.near = number("near")?.unwrap_or(camera.near);
+        camera.far = number("far")?.unwrap_or(camera.far);
+        camera.ortho_height = number("ortho_height")?.unwrap_or(camera.ortho_height);
+        if let Some(orthographic) =
+            self.optional(value, "orthographic", at, |v, at| self.boolean(v, at))?
+        {
+            camera.orthographic = orthographic;
+        }
+        Ok(camera)
+    }
+
+    fn object(
+        &mut self,
+        value: &Json,
+        at: &str,
+        scene: &mut Scene,
+        parent: Option<NodeId>,
+    ) -> io::Result<()> {
+        self.check_keys(
+            value,
+            at,
+            &[
+                "mesh", "texture", "material", "lod", "position", "rotation", "scale", "children",
+            ],
+        )?;
+
+        let mesh_path = value
+            .get("mesh")
+            .ok_or_else(|| self.error(at, "missing mesh"))?;
+        let mesh_path = self.string(mesh_path, &format!("{at}.mesh"))?;
+        let mesh_path = mesh_path.to_string();
+        let texture = match self.optional(value, "texture", at, |v, at| self.string(v, at))? {
+            Some(relative) => Some(self.texture(relative, &format!("{at}.texture"))?),
+            None => None,
+        };
+        // Faces only carry texture coordinates when there is a texture to use them with
+        let mesh = self.mesh(&mesh_path, texture.is_some(), &format!("{at}.mesh"))?;
+
+        let material = match value.get("material") {
+            Some(Json::Null) | None => Rc::clone(&self.default_material),
+            Some(material) => self.material(material, &format!("{at}.material"))?,
+        };
+
+        let lod = match value.get("lod") {
+            Some(Json::Null) | None => None,
+            Some(lod) => Some(self.lod(lod, texture.is_some(), &format!("{at}.lod"))?),
+        };
+
+        let rotation = self
+            .optional(value, "rotation", at, |v, at| self.vector(v, at))?
+            .unwrap_or_default();
+        // A single number scales evenly
+        let scale = self
+            .optional(value, "scale", at, |v, at| match v {
+                Json::Number(scale) => Ok(Vector3::new(*scale, *scale, *scale)),
+                _ => self.vector(v, at),
+            })?
+            .unwrap_or(Vector3::new(1.0, 1.0, 1.0));
+
+        let object = Object {
+            mesh,
+            position: self
+                .optional(value, "position", at, |v, at| self.vector(v, at))?
+                .unwrap_or_default(),
+            rotation: Vector3::new(
+                rotation.x.to_radians(),
+                rotation.y.to_radians(),
+                rotation.z.to_radians(),
+            ),
+            scale,
+            texture,
+            material,
+            lod,
+        };
+        let id = match parent {
+            Some(parent) => scene.add_child(parent, object),
+            None => scene.add(object),
+        };
+
+        if let Some(children) = value.get("children") {
+            let children = children
+                .as_array()
+                .ok_or_else(|| self.error(&format!("{at}.children"), "expected an array"))?;
+            for (i, child) in children.iter().enumerate() {
+                self.object(child, &format!("{at}.children[{i}]"), scene, Some(id))?;
+            }
+        }
+        Ok(())
+    }
+
+    // OBJ or PLY, going by the extension. "cube" is built in
+    fn mesh(&mut self, relative: &str, has_texture: bool, at: &str) -> io::Result<Rc<Mesh>> {
+        if relative == "cube" {
+            return Ok(self.assets.cube());
+        }
+
+        let path = self.path(relative);
+        self.assets
+            .mesh(&path, has_texture)
+            .map_err(|err| self.error(at, &format!("{relative}: {err}")))
+    }
+
+    fn texture(&mut self, relative: &str, at: &str) -> io::Result<Rc<Image>> {
+        let path = self.path(relative);
+        self.assets
+            .texture(&path)
+            .map_err(|err| self.error(at, &format!("{relative}: {err}")))
+    }
+
+    // Eg: { "metric": "distance", "levels": [{ "mesh": "teapot_low.obj", "switch_at": 20 }] }
+    fn lod(&mut self, value: &Json, has_texture: bool, at: &str) -> io::Result<Lod> {
+        self.check_keys(value, at, &["metric", "levels", "hysteresis"])?;
+        let metric = value
+            .get("metric")
+            .ok_or_else(|| self.error(at, "missing metric"))?;
+        let metric = match self.string(metric, &format!("{at}.metric"))? {
+            "distance" => LodMetric::Distance,
+            "screen_size" => LodMetric::ScreenSize,
+            metric => {
+                return Err(self.error(
+                    at,
+                    &format!("unknown metric {metric}, expected distance or screen_size"),
+                ));
+            }
+        };
+
+        let levels = value
+            .get("levels")
+            .and_then(Json::as_array)
+            .ok_or_else(|| self.error(&format!("{at}.levels"), "expected an array"))?;
+        let mut lod_levels = Vec::with_capacity(levels.len());
+        for (i, level) in levels.iter().enumerate() {
+            let at = format!("{at}.levels[{i}]");
+            self.check_keys(level, &at, &["mesh", "switch_at"])?;
+            let field = |key: &str| {
+                level
+                    .get(key)
+                    .ok_or_else(|| self.error(&at, &format!("missing {key}")))
+            };
+            let mesh = self.string(field("mesh")?, &format!("{at}.mesh"))?;
+            let switch_at = self.number(field("switch_at")?, &format!("{at}.switch_at"))?;
+            if switch_at <= 0.0 {
+                return Err(self.error(&format!("{at}.switch_at"), "expected a positive number"));
+            }
+            // Each level takes over further away, or smaller on screen, than the one before
+            if let Some(previous) = lod_levels.last().map(|level: &LodLevel| level.switch_at) {
+                let in_order = match metric {
+                    LodMetric::Distance => switch_at > previous,
+                    LodMetric::ScreenSize => switch_at < previous,
+                };
+                if !in_order {
+                    let order = match metric {
+                        LodMetric::Distance => "larger",
+                        LodMetric::ScreenSize => "smaller",
+                    };
+                    return Err(self.error(
+                        &format!("{at}.switch_at"),
+                        &format!("expected a {order} switch_at than the level before"),
+                    ));
+                }
+            }
+            let mesh = mesh.to_string();
+            lod_levels.push(LodLevel {
+                mesh: self.mesh(&mesh, has_texture, &format!("{at}.mesh"))?,
+                switch_at,
+            });
+        }
+
+        let hysteresis = self
+            .optional(value, "hysteresis", at, |v, at| self.number(v, at))?
+            .unwrap_or(0.0);
+        if !(0.0..1.0).contains(&hysteresis) {
+            return Err(self.error(
+                &format!("{at}.hysteresis"),
+                "expected a number from 0 up to but not including 1",
+            ));
+        }
+        Ok(Lod::new(metric, lod_levels).with_hysteresis(hysteresis))
+    }
+
+    // Either the path of an MTL file or the settings themselves
+    fn material(&mut self, value: &Json, at: &str) -> io::Result<Rc<Material>> {
+        if let Some(relative) = value.as_str() {
+            let path = self.path(relative);
+            return self
+                .assets
+                .material(&path)
+                .map_err(|err| self.error(at, &format!("{relative}: {err}")));
+        }
+
+        self.check_keys(
+            value,
+            at,
+            &[
+                "reflectivity",
+                "opacity",
+                "alpha_cutoff",
+                "emissive",
+                "emissive_texture",
+            ],
+        )?;
+        let mut material = Material::default();
+        let number = |key| self.optional(value, key, at, |v, at| self.number(v, at));
+
+        material.reflectivity = number("reflectivity")?.unwrap_or(material.reflectivity);
+        material.opacity = number("opacity")?.unwrap_or(material.opacity);
+        if let Some(cutoff) = number("alpha_cutoff")? {
+            material.alpha_mode = AlphaMode::Mask(cutoff);
+        }
+        if material.opacity < 1.0 {
+            material.alpha_mode = AlphaMode::Blend;
+        }
+
+        let key = "emissive_texture";
+        if let Some(relative) = self.optional(value, key, at, |v, at| self.string(v, at))? {
+            material.emissive_texture = Some(self.texture(relative, &format!("{at}.{key}"))?);
+        }
+        // A glow map on its own glows at full strength, as with MTL files
+        let default_emissive = if material.emissive_texture.is_some() {
+            WHITE
+        } else {
+            material.emissive
+        };
+        material.emissive = self
+            .optional(value, "emissive", at, |v, at| self.color(v, at))?
+            .unwrap_or(default_emissive);
+
+        Ok(Rc::new(material))
+    }
+
+    // Eg: { "type": "gradient", "zenith": "#285aaa", "horizon": "#bed7eb", "ground": "#3c3732" }
+    fn skybox(&mut self, value: &Json, at: &str) -> io::Result<Skybox> {
+        let kind = value
+            .get("type")
+            .ok_or_else(|| self.error(at, "missing type"))?;
+        match self.string(kind, &format!("{at}.type"))? {
+            "gradient" => {
+                self.check_keys(value, at, &["type", "zenith", "horizon", "ground"])?;
+                let color = |key: &str| {
+                    let field = value
+                        .get(key)
+                        .ok_or_else(|| self.error(at, &format!("missing {key}")))?;
+                    self.color(field, &format!("{at}.{key}"))
+                };
+                Ok(Skybox::Gradient {
+                    zenith: color("zenith")?,
+                    horizon: color("horizon")?,
+                    ground: color("ground")?,
+                })
+            }
+            "equirectangular" => {
+                self.check_keys(value, at, &["type", "image"])?;
+                let image = value
+                    .get("image")
+                    .ok_or_else(|| self.error(at, "missing image"))?;
+                let image = self.string(image, &format!("{at}.image"))?;
+                let image = self.texture(image, &format!("{at}.image"))?;
+                Ok(Skybox::Equirectangular(image))
+            }
+            "cubemap" => {
+                self.check_keys(value, at, &["type", "faces"])?;
+                let faces = match value.get("faces").and_then(Json::as_array) {
+                    Some(faces) if faces.len() == 6 => faces,
+                    _ => return Err(self.error(at, "expected 6 faces: +x, -x, +y, -y, +z, -z")),
+                };
+                let faces = faces
+                    .iter()
+                    .map(|face| {
+                        let face = self.string(face, &format!("{at}.faces"))?;
+                        self.texture(face, &format!("{at}.faces"))
+                    })
+                    .collect::<io::Result<Vec<_>>>()?;
+                Ok(Skybox::Cubemap(faces.try_into().unwrap()))
+            }
+            kind => Err(self.error(
+                at,
+                &format!(
+                    "unknown skybox type {kind}, expected gradient, equirectangular or cubemap"
+                ),
+            )),
+        }
+    }
+
+    // Eg: { "type": "linear", "start": 40.0, "end": 100.0, "color": "#bed7eb" }
+    fn fog(&self, value: &Json, at: &str) -> io::Result<Fog> {
+        let number = |key: &str| {
+            let field = value
+                .get(key)
+                .ok_or_else(|| self.error(at, &format!("missing {key}")))?;
+            self.number(field, &format!("{at}.{key}"))
+        };
+        let kind = value
+            .get("type")
+            .ok_or_else(|| self.error(at, "missing type"))?;
+        let mode = match self.string(kind, &format!("{at}.type"))? {
+            "linear" => {
+                self.check_keys(value, at, &["type", "color", "start", "end"])?;
+                FogMode::Linear {
+                    start: number("start")?,
+                    end: number("end")?,
+                }
+            }
+            "exponential" => {
+                self.check_keys(value, at, &["type", "color", "density"])?;
+                FogMode::Exponential {
+                    density: number("density")?,
+                }
+            }
+            "height" => {
+                self.check_keys(
+                    value,
+                    at,
+                    &["type", "color", "density", "falloff", "base_height"],
+                )?;
+                FogMode::Height {
+                    density: number("density")?,
+                    falloff: number("falloff")?,
+                    base_height: number("base_height")?,
+                }
+            }
+            kind => {
+                return Err(self.error(
+                    at,
+                    &format!("unknown fog type {kind}, expected linear, exponential or height"),
+                ));
+            }
+        };
+
+        let color = value
+            .get("color")
+            .ok_or_else(|| self.error(at, "missing color"))?;
+        Ok(Fog::new(mode, self.color(color, &format!("{at}.color"))?))
+    }
+
+    // Any settings left out keep their defaults -> {} is the default bloom
+    fn bloom(&self, value: &Json, at: &str) -> io::Result<Bloom> {
+        self.check_keys(
+            value,
+            at,
+            &["threshold", "intensity", "radius", "downsample"],
+        )?;
+        let mut bloom = Bloom::default();
+        let number = |key| self.optional(value, key, at, |v, at| self.number(v, at));
+
+        bloom.threshold = number("threshold")?.unwrap_or(bloom.threshold);
+        bloom.intensity = number("intensity")?.unwrap_or(bloom.intensity);
+        bloom.radius = number("radius")?.map_or(bloom.radius, |radius| radius as usize);
+        bloom.downsample = number("downsample")?.map_or(bloom.downsample, |d| d as usize);
+        Ok(bloom)
+    }
+}
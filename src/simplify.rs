@@ -0,0 +1,520 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+};
+
+use crate::{
+    Vector3,
+    matrix::Vector2,
+    mesh::{Face, Mesh, placeholder_texture_coords},
+};
+
+// Borders and UV seams are held in place by planes along them, counted this much more than the
+// surface itself -> They can still slide along their own line
+const BOUNDARY_WEIGHT: f64 = 1000.0;
+
+type Point = [f64; 3];
+
+// Sum of squared distances to a set of planes, as the symmetric 4x4 matrix of the plane
+// equations -> The error of moving a vertex to a point, Garland and Heckbert style
+#[derive(Debug, Clone, Copy, Default)]
+struct Quadric {
+    // aa, ab, ac, ad, bb, bc, bd, cc, cd, dd
+    m: [f64; 10],
+}
+
+impl Quadric {
+    // Plane through `point` facing `normal`, which must be of unit length
+    fn plane(normal: Point, point: Point, weight: f64) -> Self {
+        let [a, b, c] = normal;
+        let d = -dot(normal, point);
+        let m = [
+            a * a,
+            a * b,
+            a * c,
+            a * d,
+            b * b,
+            b * c,
+            b * d,
+            c * c,
+            c * d,
+            d * d,
+        ];
+        Quadric {
+            m: m.map(|value| value * weight),
+        }
+    }
+
+    fn add(&self, other: &Quadric) -> Quadric {
+        Quadric {
+            m: std::array::from_fn(|i| self.m[i] + other.m[i]),
+        }
+    }
+
+    fn error(&self, [x, y, z]: Point) -> f64 {
+        let m = &self.m;
+        m[0] * x * x
+            + 2.0 * m[1] * x * y
+            + 2.0 * m[2] * x * z
+            + 2.0 * m[3] * x
+            + m[4] * y * y
+            + 2.0 * m[5] * y * z
+            + 2.0 * m[6] * y
+            + m[7] * z * z
+            + 2.0 * m[8] * z
+            + m[9]
+    }
+
+    // Point with the smallest error, if there is a single one -> None when the planes are all
+    // parallel or meet along a line, eg: on a flat patch
+    fn optimal(&self) -> Option<Point> {
+        let m = &self.m;
+        let rows = [[m[0], m[1], m[2]], [m[1], m[4], m[5]], [m[2], m[5], m[7]]];
+        let rhs = [-m[3], -m[6], -m[8]];
+
+        let det = determinant(rows);
+        let scale = rows
+            .iter()
+            .flatten()
+            .fold(0.0, |max: f64, v| max.max(v.abs()));
+        if det.abs() <= 1e-10 * scale.powi(3) {
+            return None;
+        }
+
+        // Cramer's rule -> Swap each column for the right hand side in turn
+        Some(std::array::from_fn(|column| {
+            let mut rows = rows;
+            for (row, value) in rows.iter_mut().zip(rhs) {
+                row[column] = value;
+            }
+            determinant(rows) / det
+        }))
+    }
+}
+
+// Moves `from` onto `to`, which then sits at `position`
+#[derive(Debug)]
+struct Collapse {
+    cost: f64,
+    from: usize,
+    to: usize,
+    position: Point,
+    // Versions of both vertices when this was worked out -> Out of date once either one changes
+    versions: (u32, u32),
+}
+
+// Cheapest collapse first in a BinaryHeap
+impl Ord for Collapse {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+struct Simplifier {
+    positions: Vec<Point>,
+    faces: Vec<Face>,
+    removed_faces: Vec<bool>,
+    face_count: usize,
+    // Faces using each vertex, without removed ones
+    vertex_faces: Vec<Vec<usize>>,
+    quadrics: Vec<Quadric>,
+    // Bumped whenever a vertex moves or goes away
+    versions: Vec<u32>,
+    // Vertices that have to stay where they are, eg: on a UV seam
+    locked: Vec<bool>,
+    // With real texture coordinates, vertices only ever move onto a neighbour, so the texture
+    // coordinates they already have stay right
+    textured: bool,
+    queue: BinaryHeap<Collapse>,
+}
+
+// Collapses edges one at a time, always the one that moves the surface the least, until there are
+// `target_face_count` faces left or nothing more can go without tearing or folding the mesh
+pub(crate) fn simplify(mesh: &Mesh, target_face_count: usize) -> Mesh {
+    let mut simplifier = Simplifier::new(mesh);
+    while simplifier.face_count > target_face_count {
+        let Some(collapse) = simplifier.queue.pop() else {
+            break;
+        };
+        if simplifier.is_valid(&collapse) {
+            simplifier.apply(&collapse);
+        }
+    }
+    simplifier.finish(mesh)
+}
+
+impl Simplifier {
+    fn new(mesh: &Mesh) -> Self {
+        let placeholder = placeholder_texture_coords();
+        let textured = mesh
+            .faces
+            .iter()
+            .any(|face| !same_texture_coords(&face.texture_coords, &placeholder));
+
+        let positions: Vec<Point> = mesh
+            .vertices
+            .iter()
+            .map(|v| [v.x as f64, v.y as f64, v.z as f64])
+            .collect();
+        let faces = mesh.faces.clone();
+
+        // Faces with a repeated vertex have no area, so they can go straight away
+        let removed_faces: Vec<bool> = faces
+            .iter()
+            .map(|face| {
+                let [a, b, c] = face.vertices;
+                a == b || b == c || c == a
+            })
+            .collect();
+        let face_count = removed_faces.iter().filter(|removed| !**removed).count();
+
+        let mut simplifier = Simplifier {
+            vertex_faces: vec![Vec::new(); positions.len()],
+            quadrics: vec![Quadric::default(); positions.len()],
+            versions: vec![0; positions.len()],
+            locked: vec![false; positions.len()],
+            positions,
+            faces,
+            removed_faces,
+            face_count,
+            textured,
+            queue: BinaryHeap::new(),
+        };
+
+        let mut edge_faces: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        for (index, face) in simplifier.faces.iter().enumerate() {
+            if simplifier.removed_faces[index] {
+                continue;
+            }
+            for (i, &v) in face.vertices.iter().enumerate() {
+                simplifier.vertex_faces[v].push(index);
+                let next = face.vertices[(i + 1) % 3];
+                edge_faces.entry(edge_key(v, next)).or_default().push(index);
+            }
+
+            // Each face's plane, counted by its area so small faces don't outweigh large ones
+            let [a, b, c] = face.vertices.map(|v| simplifier.positions[v]);
+            let normal = cross(sub(b, a), sub(c, a));
+            let length = dot(normal, normal).sqrt();
+            if length > 0.0 {
+                let quadric = Quadric::plane(normal.map(|n| n / length), a, length / 2.0);
+                for v in face.vertices {
+                    simplifier.quadrics[v] = simplifier.quadrics[v].add(&quadric);
+                }
+            }
+        }
+
+        // A vertex with more than one set of texture coordinates sits on a seam
+        if textured {
+            for (v, faces) in simplifier.vertex_faces.iter().enumerate() {
+                let mut coords = faces
+                    .iter()
+                    .map(|&face| simplifier.texture_coords_at(face, v));
+                if let Some(first) = coords.next() {
+                    simplifier.locked[v] = coords.any(|coords| !same_uv(&coords, &first));
+                }
+            }
+        }
+
+        for (&(a, b), faces) in &edge_faces {
+            let boundary = match faces.as_slice() {
+                // Border
+                [_] => true,
+                [f1, f2] => {
+                    simplifier.textured
+                        && [a, b].iter().any(|&v| {
+                            !same_uv(
+                                &simplifier.texture_coords_at(*f1, v),
+                                &simplifier.texture_coords_at(*f2, v),
+                            )
+                        })
+                }
+                // Shared by more than two faces -> Too tangled to collapse safely
+                _ => {
+                    simplifier.locked[a] = true;
+                    simplifier.locked[b] = true;
+                    false
+                }
+            };
+            if !boundary {
+                continue;
+            }
+
+            // Plane through the edge at right angles to each face beside it -> Moving away from
+            // the edge costs, moving along it doesn't
+            let (pa, pb) = (simplifier.positions[a], simplifier.positions[b]);
+            let edge = sub(pb, pa);
+            for &face in faces {
+                let [p0, p1, p2] = simplifier.faces[face]
+                    .vertices
+                    .map(|v| simplifier.positions[v]);
+                let normal = cross(edge, cross(sub(p1, p0), sub(p2, p0)));
+                let length = dot(normal, normal).sqrt();
+                if length > 0.0 {
+                    let quadric = Quadric::plane(
+                        normal.map(|n| n / length),
+                        pa,
+                        BOUNDARY_WEIGHT * dot(edge, edge),
+                    );
+                    simplifier.quadrics[a] = simplifier.quadrics[a].add(&quadric);
+                    simplifier.quadrics[b] = simplifier.quadrics[b].add(&quadric);
+                }
+            }
+        }
+
+        for &(a, b) in edge_faces.keys() {
+            simplifier.push(a, b);
+        }
+        simplifier
+    }
+
+    // Queues the cheapest way of collapsing the edge between `a` and `b`, if there is one
+    fn push(&mut self, a: usize, b: usize) {
+        let quadric = self.quadrics[a].add(&self.quadrics[b]);
+
+        let mut options = Vec::with_capacity(3);
+        if !self.locked[a] {
+            options.push((a, b, self.positions[b]));
+        }
+        if !self.locked[b] {
+            options.push((b, a, self.positions[a]));
+        }
+        // Without texture coordinates to keep, both can move to wherever fits the surface best
+        if !self.textured && !self.locked[a] && !self.locked[b] {
+            let midpoint =
+                std::array::from_fn(|i| (self.positions[a][i] + self.positions[b][i]) / 2.0);
+            options.push((a, b, quadric.optimal().unwrap_or(midpoint)));
+        }
+
+        let best = options
+            .into_iter()
+            .map(|(from, to, position)| (quadric.error(position), from, to, position))
+            .min_by(|(cost1, ..), (cost2, ..)| cost1.total_cmp(cost2));
+        if let Some((cost, from, to, position)) = best {
+            self.queue.push(Collapse {
+                cost,
+                from,
+                to,
+                position,
+                versions: (self.versions[from], self.versions[to]),
+            });
+        }
+    }
+
+    fn is_valid(&self, collapse: &Collapse) -> bool {
+        let Collapse { from, to, .. } = *collapse;
+        if collapse.versions != (self.versions[from], self.versions[to]) {
+            return false;
+        }
+
+        // Link condition -> The only vertices both ends share are the ones across the faces on
+        // the edge. Otherwise the collapse would pinch the surface into a non-manifold mess
+        let shared_faces = self.shared_faces(from, to);
+        if shared_faces.is_empty() {
+            return false;
+        }
+        let neighbours_from = self.neighbours(from);
+        let neighbours_to = self.neighbours(to);
+        let common = neighbours_from
+            .iter()
+            .filter(|v| neighbours_to.contains(v))
+            .count();
+        if common != shared_faces.len() {
+            return false;
+        }
+
+        // Joining two borders through the middle of the surface would pinch it too
+        if shared_faces.len() > 1 && self.is_on_border(from) && self.is_on_border(to) {
+            return false;
+        }
+
+        // Both faces on the edge have to agree on where `to` is in the texture, as the faces
+        // around `from` are about to take that over
+        if self.textured {
+            let coords = self.texture_coords_at(shared_faces[0], to);
+            if shared_faces[1..]
+                .iter()
+                .any(|&face| !same_uv(&self.texture_coords_at(face, to), &coords))
+            {
+                return false;
+            }
+        }
+
+        // No face that stays may turn over
+        for v in [from, to] {
+            for &face in &self.vertex_faces[v] {
+                if shared_faces.contains(&face) {
+                    continue;
+                }
+                let old = self.faces[face].vertices.map(|v| self.positions[v]);
+                let new = self.faces[face].vertices.map(|v| {
+                    if v == from || v == to {
+                        collapse.position
+                    } else {
+                        self.positions[v]
+                    }
+                });
+                let old_normal = cross(sub(old[1], old[0]), sub(old[2], old[0]));
+                let new_normal = cross(sub(new[1], new[0]), sub(new[2], new[0]));
+                if dot(old_normal, new_normal) <= 0.0 {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    fn apply(&mut self, collapse: &Collapse) {
+        let Collapse {
+            from, to, position, ..
+        } = *collapse;
+
+        let shared_faces = self.shared_faces(from, to);
+        let to_coords = self.texture_coords_at(shared_faces[0], to);
+
+        for face in std::mem::take(&mut self.vertex_faces[from]) {
+            if shared_faces.contains(&face) {
+                self.removed_faces[face] = true;
+                self.face_count -= 1;
+                for v in self.faces[face].vertices {
+                    self.vertex_faces[v].retain(|&f| f != face);
+                }
+                continue;
+            }
+
+            let corner = self.corner(face, from);
+            self.faces[face].vertices[corner] = to;
+            if self.textured {
+                self.faces[face].texture_coords[corner] = to_coords;
+            }
+            self.vertex_faces[to].push(face);
+        }
+
+        self.positions[to] = position;
+        self.quadrics[to] = self.quadrics[to].add(&self.quadrics[from]);
+        self.versions[from] += 1;
+        self.versions[to] += 1;
+
+        // Only edges touching `to` cost anything different now
+        for neighbour in self.neighbours(to) {
+            self.push(to, neighbour);
+        }
+    }
+
+    // Drops removed faces and the vertices only they used
+    fn finish(&self, mesh: &Mesh) -> Mesh {
+        let mut new_index = vec![None; self.positions.len()];
+        let mut vertices = Vec::new();
+        let mut colors = Vec::new();
+        let mut faces = Vec::with_capacity(self.face_count);
+
+        for (face, removed) in self.faces.iter().zip(&self.removed_faces) {
+            if *removed {
+                continue;
+            }
+            let vertices = face.vertices.map(|v| {
+                *new_index[v].get_or_insert_with(|| {
+                    let [x, y, z] = self.positions[v];
+                    vertices.push(Vector3::new(x as f32, y as f32, z as f32));
+                    if let Some(mesh_colors) = &mesh.colors {
+                        colors.push(mesh_colors[v]);
+                    }
+                    vertices.len() - 1
+                })
+            });
+            faces.push(Face {
+                vertices,
+                texture_coords: face.texture_coords,
+            });
+        }
+
+        Mesh::new(vertices, faces, mesh.colors.is_some().then_some(colors))
+    }
+
+    fn corner(&self, face: usize, v: usize) -> usize {
+        self.faces[face]
+            .vertices
+            .iter()
+            .position(|&corner| corner == v)
+            .unwrap_or(0)
+    }
+
+    fn texture_coords_at(&self, face: usize, v: usize) -> Vector2 {
+        self.faces[face].texture_coords[self.corner(face, v)]
+    }
+
+    fn shared_faces(&self, a: usize, b: usize) -> Vec<usize> {
+        self.vertex_faces[a]
+            .iter()
+            .copied()
+            .filter(|&face| self.faces[face].vertices.contains(&b))
+            .collect()
+    }
+
+    fn neighbours(&self, v: usize) -> Vec<usize> {
+        let mut neighbours = Vec::new();
+        for &face in &self.vertex_faces[v] {
+            for other in self.faces[face].vertices {
+                if other != v && !neighbours.contains(&other) {
+                    neighbours.push(other);
+                }
+            }
+        }
+        neighbours
+    }
+
+    // Whether any edge from `v` has a face on one side only
+    fn is_on_border(&self, v: usize) -> bool {
+        self.neighbours(v)
+            .into_iter()
+            .any(|neighbour| self.shared_faces(v, neighbour).len() == 1)
+    }
+}
+
+fn edge_key(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+fn same_uv(a: &Vector2, b: &Vector2) -> bool {
+    a.u == b.u && a.v == b.v
+}
+
+fn same_texture_coords(a: &[Vector2; 3], b: &[Vector2; 3]) -> bool {
+    a.iter().zip(b).all(|(a, b)| same_uv(a, b))
+}
+
+fn sub(a: Point, b: Point) -> Point {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: Point, b: Point) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: Point, b: Point) -> Point {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn determinant(m: [[f64; 3]; 3]) -> f64 {
+    m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+}
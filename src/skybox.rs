@@ -0,0 +1,143 @@
+use std::{f32::consts::PI, rc::Rc};
+
+use macroquad::{color::Color, texture::Image};
+
+use crate::{
+    Camera, EMPTY_DEPTH, Fog, Vector3,
+    material::sample_texture,
+    matrix::{Mat4x4, is_orthographic, vec_add, vec_mul},
+};
+
+pub enum Skybox {
+    // A single panorama with longitude along the width and latitude along the height
+    Equirectangular(Rc<Image>),
+    // Faces in the order +X, -X, +Y, -Y, +Z, -Z, each as seen from inside the cube.
+    // The +Y face has +Z along its bottom edge and the -Y face has +Z along its top edge
+    Cubemap([Rc<Image>; 6]),
+    // Plain vertical gradient for when there is no sky texture at hand
+    Gradient {
+        zenith: Color,
+        horizon: Color,
+        ground: Color,
+    },
+}
+
+impl Skybox {
+    // Colour of the sky seen when looking along a world space direction
+    pub fn sample(&self, direction: &Vector3) -> Color {
+        let direction = direction.normalize();
+
+        match self {
+            Skybox::Equirectangular(image) => {
+                // Looking down +Z, the right side of the screen is -X
+                let u = 0.5 + (-direction.x).atan2(direction.z) / (2.0 * PI);
+                let v = 0.5 - direction.y.clamp(-1.0, 1.0).asin() / PI;
+                sample_texture(image, u, v)
+            }
+            Skybox::Cubemap(faces) => {
+                let x = direction.x.abs();
+                let y = direction.y.abs();
+                let z = direction.z.abs();
+
+                // Pick the face the direction points at the most, then project onto it
+                let (face, u, v) = if x >= y && x >= z {
+                    if direction.x > 0.0 {
+                        (0, direction.z / x, -direction.y / x)
+                    } else {
+                        (1, -direction.z / x, -direction.y / x)
+                    }
+                } else if y >= z {
+                    if direction.y > 0.0 {
+                        (2, -direction.x / y, direction.z / y)
+                    } else {
+                        (3, -direction.x / y, -direction.z / y)
+                    }
+                } else if direction.z > 0.0 {
+                    (4, -direction.x / z, -direction.y / z)
+                } else {
+                    (5, direction.x / z, -direction.y / z)
+                };
+
+                sample_texture(&faces[face], (u + 1.0) / 2.0, (v + 1.0) / 2.0)
+            }
+            Skybox::Gradient {
+                zenith,
+                horizon,
+                ground,
+            } => {
+                let (target, t) = if direction.y >= 0.0 {
+                    (zenith, direction.y)
+                } else {
+                    (ground, -direction.y)
+                };
+                Color::from_vec(horizon.to_vec().lerp(target.to_vec(), t))
+            }
+        }
+    }
+
+    // Fills every pixel that no triangle has been drawn to, so call this after drawing the scene.
+    // Fog covers the sky as if it were at the far plane, like the furthest geometry
+    pub fn draw(
+        &self,
+        image: &mut Image,
+        depth_buffer: &[f32],
+        camera: &Camera,
+        projection_mat: &Mat4x4,
+        fog: Option<&Fog>,
+    ) {
+        let width = image.width();
+        let height = image.height();
+        let view_mat = camera.return_view_mat();
+
+        // Direction has a view space depth of 1 -> Scaled by far, it ends on the far plane
+        let sky_color = |direction: &Vector3| {
+            let color = self.sample(direction);
+            match fog {
+                Some(fog) => {
+                    let camera_height = camera.position.y;
+                    let sky_height = camera_height + direction.y * camera.far;
+                    let amount = fog.amount(camera.far, camera_height, sky_height);
+                    Color::from_vec(color.to_vec().lerp(fog.color.to_vec(), amount))
+                }
+                None => color,
+            }
+        };
+
+        // Camera axes in world space -> The view matrix stores them as its columns
+        let right = Vector3::new(view_mat[0][0], view_mat[1][0], view_mat[2][0]);
+        let up = Vector3::new(view_mat[0][1], view_mat[1][1], view_mat[2][1]);
+        let forward = Vector3::new(view_mat[0][2], view_mat[1][2], view_mat[2][2]);
+
+        // Orthographic rays are all parallel, so the whole background is the sky straight ahead
+        if is_orthographic(projection_mat) {
+            let color = sky_color(&forward);
+            for (i, &depth) in depth_buffer.iter().enumerate() {
+                if depth == EMPTY_DEPTH {
+                    image.set_pixel((i % width) as u32, (i / width) as u32, color);
+                }
+            }
+            return;
+        }
+
+        for y in 0..height {
+            // Undo the projection for a point one unit in front of the camera
+            let ndc_y = (y as f32 + 0.5) / height as f32 * 2.0 - 1.0;
+            let view_y = -ndc_y / projection_mat[1][1];
+
+            for x in 0..width {
+                if depth_buffer[x + y * width] != EMPTY_DEPTH {
+                    continue;
+                }
+
+                let ndc_x = (x as f32 + 0.5) / width as f32 * 2.0 - 1.0;
+                let view_x = -ndc_x / projection_mat[0][0];
+
+                let direction = vec_add(
+                    &forward,
+                    &vec_add(&vec_mul(&right, view_x), &vec_mul(&up, view_y)),
+                );
+                image.set_pixel(x as u32, y as u32, sky_color(&direction));
+            }
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use macroquad::texture::Image;
+
+use crate::{
+    Vector3,
+    matrix::Vector2,
+    mesh::{Face, Mesh},
+};
+
+// Ground generated from a greyscale image, eg: a DEM -> Each pixel is a point on a grid on the xz
+// plane, raised by how bright it is. Black is at y = 0 and white at y = `height_scale`, with
+// `spacing` between neighbouring points. The image's top row is at -z and its left column at -x,
+// centred on the origin, and the texture is stretched over all of it the same way round
+impl Mesh {
+    pub fn from_heightmap(image: &Image, spacing: f32, height_scale: f32) -> Self {
+        let heightmap = Heightmap::new(image, spacing, height_scale);
+        heightmap.mesh(
+            0..heightmap.columns.saturating_sub(1),
+            0..heightmap.rows.saturating_sub(1),
+        )
+    }
+
+    // The same ground split into squares of up to `chunk_size` by `chunk_size` grid cells, row by
+    // row from the top left of the image -> Chunks out of view get culled on their own instead of
+    // the whole terrain being drawn. They are placed as parts of one terrain, so they line up at
+    // the origin
+    pub fn heightmap_chunks(
+        image: &Image,
+        spacing: f32,
+        height_scale: f32,
+        chunk_size: usize,
+    ) -> Vec<Self> {
+        let heightmap = Heightmap::new(image, spacing, height_scale);
+        let chunk_size = chunk_size.max(1);
+        let (cells_x, cells_z) = (
+            heightmap.columns.saturating_sub(1),
+            heightmap.rows.saturating_sub(1),
+        );
+
+        let mut chunks = Vec::new();
+        for z in (0..cells_z).step_by(chunk_size) {
+            for x in (0..cells_x).step_by(chunk_size) {
+                chunks.push(heightmap.mesh(
+                    x..(x + chunk_size).min(cells_x),
+                    z..(z + chunk_size).min(cells_z),
+                ));
+            }
+        }
+        chunks
+    }
+}
+
+struct Heightmap {
+    // Row by row, like the image's pixels
+    heights: Vec<f32>,
+    columns: usize,
+    rows: usize,
+    spacing: f32,
+}
+
+impl Heightmap {
+    fn new(image: &Image, spacing: f32, height_scale: f32) -> Self {
+        let (columns, rows) = (image.width as usize, image.height as usize);
+        // Perceived brightness -> Greyscale images come out as their grey level, coloured ones
+        // still give something sensible
+        let heights = image
+            .get_image_data()
+            .iter()
+            .map(|&[r, g, b, _]| {
+                let brightness = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+                brightness / 255.0 * height_scale
+            })
+            .collect();
+
+        Heightmap {
+            heights,
+            columns,
+            rows,
+            spacing,
+        }
+    }
+
+    // Grid cells from `cells_x` and `cells_z` as a mesh, with the vertices on their corners
+    fn mesh(&self, cells_x: std::ops::Range<usize>, cells_z: std::ops::Range<usize>) -> Mesh {
+        // Anything under 2x2 pixels has no cells
+        if self.columns < 2 || self.rows < 2 {
+            return Mesh::new(Vec::new(), Vec::new(), None);
+        }
+
+        let (width, depth) = (cells_x.len() + 1, cells_z.len() + 1);
+        let mut vertices = Vec::with_capacity(width * depth);
+        for j in cells_z.start..=cells_z.end {
+            for i in cells_x.start..=cells_x.end {
+                vertices.push(Vector3::new(
+                    (i as f32 - (self.columns - 1) as f32 / 2.0) * self.spacing,
+                    self.heights[j * self.columns + i],
+                    (j as f32 - (self.rows - 1) as f32 / 2.0) * self.spacing,
+                ));
+            }
+        }
+
+        // Texture coordinates across the whole terrain, not just this chunk
+        let corner = |i: usize, j: usize| {
+            let index = (j - cells_z.start) * width + (i - cells_x.start);
+            let uv = Vector2::new(
+                i as f32 / (self.columns - 1) as f32,
+                j as f32 / (self.rows - 1) as f32,
+            );
+            (index, uv)
+        };
+
+        let mut faces = Vec::with_capacity(cells_x.len() * cells_z.len() * 2);
+        for j in cells_z.clone() {
+            for i in cells_x.clone() {
+                // Wound like Mesh::plane, so the faces point up
+                let [a, b, c, d] = [
+                    corner(i, j),
+                    corner(i, j + 1),
+                    corner(i + 1, j + 1),
+                    corner(i + 1, j),
+                ];
+                for [x, y, z] in [[a, b, c], [a, c, d]] {
+                    faces.push(Face {
+                        vertices: [x.0, y.0, z.0],
+                        texture_coords: [x.1, y.1, z.1],
+                    });
+                }
+            }
+        }
+
+        Mesh::new(vertices, faces, None)
+    }
+}
@@ -0,0 +1,44 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+// Notices files changing on disk by checking their modification times whenever it is asked
+#[derive(Default)]
+pub struct FileWatcher {
+    // None for files that couldn't be read -> They count as changed once they show up
+    modified: HashMap<PathBuf, Option<SystemTime>>,
+}
+
+impl FileWatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Starts from the file as it is now, so only later changes are reported
+    pub fn watch(&mut self, path: &Path) {
+        self.modified
+            .insert(path.to_path_buf(), modified_time(path));
+    }
+
+    // Files changed since they were watched or last reported
+    pub fn changed(&mut self) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+        for (path, modified) in &mut self.modified {
+            let now = modified_time(path);
+            // A file being deleted isn't worth reloading -> Wait for it to come back
+            if now.is_some() && now != *modified {
+                changed.push(path.clone());
+            }
+            *modified = now;
+        }
+        changed
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
@@ -0,0 +1,68 @@
+use graphics_engine::Mesh;
+
+// A square with texture coordinates, its face written as `face`
+fn square_obj(name: &str, face: &str) -> String {
+    let contents = format!(
+        "v -1 -1 0
+v -1 1 0
+v 1 1 0
+v 1 -1 0
+vt 0 0
+vt 0 1
+vt 1 1
+vt 1 0
+vn 0 0 -1
+{face}
+"
+    );
+    let path = std::env::temp_dir().join(format!("graphics_engine_{name}.obj"));
+    std::fs::write(&path, contents).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn reads_every_face_form_with_or_without_a_texture() {
+    for (name, face) in [
+        ("plain", "f 1 2 3 4"),
+        ("uvs", "f 1/1 2/2 3/3 4/4"),
+        ("uvs_normals", "f 1/1/1 2/2/1 3/3/1 4/4/1"),
+        ("normals", "f 1//1 2//1 3//1 4//1"),
+    ] {
+        for has_texture in [false, true] {
+            let mesh = Mesh::load_from_obj(&square_obj(name, face), has_texture)
+                .unwrap_or_else(|err| panic!("{face} with has_texture {has_texture}: {err}"));
+            assert_eq!(mesh.face_count(), 2, "{face}");
+        }
+    }
+}
+
+#[test]
+fn uses_texture_coordinates_only_with_a_texture() {
+    let path = square_obj("uv_choice", "f 1/3 2/3 3/3");
+
+    let textured = Mesh::load_from_obj(&path, true).unwrap();
+    // v is flipped so 0 is the top of the image
+    assert!(
+        textured.faces()[0]
+            .texture_coords
+            .iter()
+            .all(|uv| (uv.u, uv.v) == (1.0, 0.0))
+    );
+
+    let untextured = Mesh::load_from_obj(&path, false).unwrap();
+    assert!(
+        untextured.faces()[0]
+            .texture_coords
+            .iter()
+            .any(|uv| (uv.u, uv.v) != (1.0, 0.0))
+    );
+}
+
+#[test]
+fn rejects_missing_texture_coordinates() {
+    let path = square_obj("missing_uv", "f 1/9 2/2 3/3");
+    let err = Mesh::load_from_obj(&path, true)
+        .err()
+        .expect("a face using a missing texture coordinate loaded");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
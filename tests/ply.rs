@@ -0,0 +1,134 @@
+use graphics_engine::{Camera, EMPTY_DEPTH, Material, Mesh, Vector3, matrix};
+use macroquad::{
+    color::{BLACK, Color},
+    texture::Image,
+};
+
+const SIZE: u16 = 32;
+
+// A square facing the default camera, 3 units in front of it, as a single quad face. The face
+// has an extra property after its indices, which the loader should skip
+fn square_ply(name: &str, color: [u8; 3], uv: [f32; 2]) -> Mesh {
+    let [r, g, b] = color;
+    let [s, t] = uv;
+    let contents = format!(
+        "ply
+format ascii 1.0
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+property float s
+property float t
+element face 1
+property list uchar int vertex_indices
+property uchar flags
+end_header
+-1 -1 3 {r} {g} {b} {s} {t}
+-1 1 3 {r} {g} {b} {s} {t}
+1 1 3 {r} {g} {b} {s} {t}
+1 -1 3 {r} {g} {b} {s} {t}
+4 0 1 2 3 7
+"
+    );
+    Mesh::load_from_ply(&write_temp(name, &contents)).unwrap()
+}
+
+fn write_temp(name: &str, contents: &str) -> String {
+    let path = std::env::temp_dir().join(format!("graphics_engine_{name}.ply"));
+    std::fs::write(&path, contents).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+// Colour of the pixel in the middle of the screen, where the square is
+fn draw_center(mesh: &Mesh, texture: Option<&Image>) -> Color {
+    let camera = Camera::new();
+    let size = SIZE as f32;
+    let mut image = Image::gen_image_color(SIZE, SIZE, BLACK);
+    let mut depth_buffer = vec![EMPTY_DEPTH; SIZE as usize * SIZE as usize];
+
+    let drawn = mesh.draw(
+        size,
+        size,
+        &matrix::transform_matrix(
+            &Vector3::new(1.0, 1.0, 1.0),
+            &Vector3::new(0.0, 0.0, 0.0),
+            &Vector3::new(0.0, 0.0, 0.0),
+        ),
+        &camera.return_view_mat(),
+        &camera.position,
+        // Towards the camera, like the default scene's light
+        &Vector3::new(0.0, 0.0, -1.0),
+        &camera.projection_matrix(1.0),
+        camera.near,
+        &mut image,
+        texture,
+        &mut depth_buffer,
+        None,
+        &Material::default(),
+        None,
+        None,
+    );
+    assert!(drawn, "the square was culled");
+
+    image.get_pixel(SIZE as u32 / 2, SIZE as u32 / 2)
+}
+
+#[test]
+fn splits_quads_into_triangles() {
+    let mesh = square_ply("quad", [255, 255, 255], [0.0, 0.0]);
+
+    assert_eq!(mesh.vertex_count(), 4);
+    assert_eq!(mesh.face_count(), 2);
+
+    let origin = Vector3::new(0.5, 0.25, 0.0);
+    let forward = Vector3::new(0.0, 0.0, 1.0);
+    let (distance, _) = mesh.intersect_ray(&origin, &forward).unwrap();
+    assert!((distance - 3.0).abs() < 1e-4);
+}
+
+#[test]
+fn reads_vertex_colours() {
+    let mesh = square_ply("colours", [255, 0, 0], [0.0, 0.0]);
+    let color = draw_center(&mesh, None);
+
+    assert!(color.r > 0.0, "{color:?}");
+    assert_eq!((color.g, color.b), (0.0, 0.0), "{color:?}");
+}
+
+#[test]
+fn reads_texture_coordinates() {
+    // Left half green, right half blue -> Every corner samples the right half
+    let mut texture = Image::gen_image_color(2, 1, Color::new(0.0, 1.0, 0.0, 1.0));
+    texture.set_pixel(1, 0, Color::new(0.0, 0.0, 1.0, 1.0));
+
+    let mesh = square_ply("uvs", [255, 255, 255], [0.75, 0.5]);
+    let color = draw_center(&mesh, Some(&texture));
+
+    assert_eq!((color.r, color.g, color.b), (0.0, 0.0, 1.0), "{color:?}");
+}
+
+#[test]
+fn rejects_faces_shorter_than_their_count() {
+    let contents = "ply
+format ascii 1.0
+element vertex 3
+property float x
+property float y
+property float z
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0
+0 1 0
+1 1 0
+4 0 1 2
+";
+    let err = Mesh::load_from_ply(&write_temp("short_face", contents))
+        .err()
+        .expect("a face with 3 of its 4 vertices loaded");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
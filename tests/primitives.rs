@@ -0,0 +1,86 @@
+use graphics_engine::{
+    Mesh, Vector3,
+    matrix::{cross_product, dot_product, vec_sub},
+};
+
+// Checks every face of `mesh` winds so that cross(v1 - v0, v2 - v0) points away from
+// `inside(face centre)`, the point the face should be facing away from, and that its texture
+// coordinates stay on the texture
+fn check(name: &str, mesh: &Mesh, inside: impl Fn(&Vector3) -> Vector3) {
+    assert!(mesh.face_count() > 0, "{name} has no faces");
+
+    for (i, face) in mesh.faces().iter().enumerate() {
+        let [a, b, c] = face.vertices.map(|v| mesh.vertices()[v]);
+        let normal = cross_product(&vec_sub(&b, &a), &vec_sub(&c, &a));
+        let center = Vector3::new(
+            (a.x + b.x + c.x) / 3.0,
+            (a.y + b.y + c.y) / 3.0,
+            (a.z + b.z + c.z) / 3.0,
+        );
+        let outwards = vec_sub(&center, &inside(&center));
+        assert!(
+            dot_product(&normal, &outwards) > 0.0,
+            "{name} face {i} winds inwards"
+        );
+
+        for uv in face.texture_coords {
+            assert!(
+                (0.0..=1.0).contains(&uv.u) && (0.0..=1.0).contains(&uv.v),
+                "{name} face {i} has texture coordinates ({}, {}) off the texture",
+                uv.u,
+                uv.v
+            );
+        }
+    }
+}
+
+// The middle of the mesh's bounding box -> Inside every convex shape
+fn centroid(mesh: &Mesh) -> Vector3 {
+    let (min, max) = mesh.bounding_box();
+    Vector3::new(
+        (min.x + max.x) / 2.0,
+        (min.y + max.y) / 2.0,
+        (min.z + max.z) / 2.0,
+    )
+}
+
+#[test]
+fn convex_shapes_wind_outwards() {
+    for (name, mesh) in [
+        ("uv_sphere", Mesh::uv_sphere(1.5, 16, 8)),
+        ("icosphere", Mesh::icosphere(1.5, 2)),
+        ("cylinder", Mesh::cylinder(1.0, 3.0, 12, 3)),
+        ("cone", Mesh::cone(1.0, 2.0, 12, 2)),
+        ("capsule", Mesh::capsule(0.5, 3.0, 12, 4)),
+        ("capsule without a middle", Mesh::capsule(1.0, 2.0, 12, 4)),
+    ] {
+        let center = centroid(&mesh);
+        check(name, &mesh, |_| center);
+    }
+}
+
+#[test]
+fn torus_winds_away_from_its_tube() {
+    let radius = 2.0;
+    let mesh = Mesh::torus(radius, 0.5, 24, 12);
+
+    // The middle of the tube nearest to each face, on a circle around the y axis
+    check("torus", &mesh, |point| {
+        let distance = point.x.hypot(point.z);
+        Vector3::new(
+            point.x / distance * radius,
+            0.0,
+            point.z / distance * radius,
+        )
+    });
+}
+
+#[test]
+fn plane_faces_up() {
+    let mesh = Mesh::plane(4.0, 2.0, 4, 3);
+    assert_eq!(mesh.face_count(), 4 * 3 * 2);
+
+    check("plane", &mesh, |point| {
+        Vector3::new(point.x, point.y - 1.0, point.z)
+    });
+}
@@ -0,0 +1,122 @@
+use graphics_engine::{Mesh, Vector3};
+
+// Average gap between where rays from all around hit the original and the simplified mesh,
+// as a fraction of the original's size
+fn surface_error(original: &Mesh, simplified: &Mesh) -> f32 {
+    let (center, radius) = original.bounding_sphere();
+    let rays = 500;
+    let mut total = 0.0;
+    let mut hits = 0;
+
+    for i in 0..rays {
+        // Spread evenly over a sphere around the mesh, all pointing at its centre
+        let y = 1.0 - 2.0 * (i as f32 + 0.5) / rays as f32;
+        let angle = i as f32 * 2.399_963;
+        let ring = (1.0 - y * y).sqrt();
+        let direction = Vector3::new(-ring * angle.cos(), -y, -ring * angle.sin());
+        let origin = Vector3::new(
+            center.x - direction.x * radius * 2.0,
+            center.y - direction.y * radius * 2.0,
+            center.z - direction.z * radius * 2.0,
+        );
+
+        if let (Some((t1, _)), Some((t2, _))) = (
+            original.intersect_ray(&origin, &direction),
+            simplified.intersect_ray(&origin, &direction),
+        ) {
+            total += (t1 - t2).abs();
+            hits += 1;
+        }
+    }
+
+    assert!(hits > rays / 4, "only {hits} rays hit both meshes");
+    total / hits as f32 / radius
+}
+
+#[test]
+fn simplifies_teapot_to_target() {
+    let teapot = Mesh::load_from_obj("assets/teapot.obj", false).unwrap();
+    let simplified = teapot.simplify(1500);
+
+    assert!(simplified.face_count() <= 1500);
+    assert!(simplified.face_count() > 1400);
+    assert!(simplified.vertex_count() < teapot.vertex_count());
+
+    let error = surface_error(&teapot, &simplified);
+    assert!(
+        error < 0.01,
+        "surface moved by {error} of the teapot's size"
+    );
+}
+
+#[test]
+fn keeps_mountain_borders() {
+    let mountains = Mesh::load_from_obj("assets/mountains.obj", false).unwrap();
+    let simplified = mountains.simplify(1200);
+
+    assert!(simplified.face_count() <= 1200);
+    assert!(simplified.face_count() > 1100);
+
+    // The terrain's edges are borders, so its outline stays where it was
+    let (min, max) = mountains.bounding_box();
+    let (new_min, new_max) = simplified.bounding_box();
+    let tolerance = (max.x - min.x).max(max.z - min.z) * 0.001;
+    for (old, new) in [
+        (min.x, new_min.x),
+        (min.z, new_min.z),
+        (max.x, new_max.x),
+        (max.z, new_max.z),
+    ] {
+        assert!(
+            (old - new).abs() <= tolerance,
+            "edge moved from {old} to {new}"
+        );
+    }
+
+    // Heights straight down stay close, wherever the ground is
+    let mut total = 0.0;
+    let mut samples = 0;
+    let above = max.y + 10.0;
+    let down = Vector3::new(0.0, -1.0, 0.0);
+    for i in 1..20 {
+        for j in 1..20 {
+            let origin = Vector3::new(
+                min.x + (max.x - min.x) * i as f32 / 20.0,
+                above,
+                min.z + (max.z - min.z) * j as f32 / 20.0,
+            );
+            if let (Some((t1, _)), Some((t2, _))) = (
+                mountains.intersect_ray(&origin, &down),
+                simplified.intersect_ray(&origin, &down),
+            ) {
+                total += (t1 - t2).abs();
+                samples += 1;
+            }
+        }
+    }
+    assert!(samples > 300);
+    let error = total / samples as f32 / (max.y - min.y);
+    assert!(
+        error < 0.02,
+        "heights moved by {error} of the terrain's height"
+    );
+}
+
+#[test]
+fn keeps_uv_seams() {
+    // Every corner of the cube is on a seam between faces of the texture
+    let cube = Mesh::cube();
+    let simplified = cube.simplify(2);
+
+    assert_eq!(simplified.face_count(), cube.face_count());
+    assert_eq!(simplified.vertex_count(), cube.vertex_count());
+}
+
+#[test]
+fn leaves_meshes_under_target_alone() {
+    let teapot = Mesh::load_from_obj("assets/teapot.obj", false).unwrap();
+    let simplified = teapot.simplify(teapot.face_count());
+
+    assert_eq!(simplified.face_count(), teapot.face_count());
+    assert_eq!(simplified.vertex_count(), teapot.vertex_count());
+}
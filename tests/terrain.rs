@@ -0,0 +1,121 @@
+use graphics_engine::{
+    Mesh,
+    matrix::{cross_product, vec_sub},
+};
+use macroquad::{
+    color::{BLACK, Color},
+    texture::Image,
+};
+
+const SPACING: f32 = 2.0;
+const HEIGHT_SCALE: f32 = 10.0;
+
+// 4x4 greyscale image that gets brighter pixel by pixel, row by row
+fn heightmap() -> Image {
+    let mut image = Image::gen_image_color(4, 4, BLACK);
+    for j in 0..4 {
+        for i in 0..4 {
+            let grey = ((i + j * 4) * 17) as u8;
+            image.set_pixel(i, j, Color::from_rgba(grey, grey, grey, 255));
+        }
+    }
+    image
+}
+
+// Every corner of every face with its texture coordinates, as arrays to compare them exactly
+fn corners(mesh: &Mesh) -> Vec<([f32; 3], [f32; 2])> {
+    mesh.faces()
+        .iter()
+        .flat_map(|face| {
+            (0..3).map(|i| {
+                let (p, uv) = (mesh.vertices()[face.vertices[i]], face.texture_coords[i]);
+                ([p.x, p.y, p.z], [uv.u, uv.v])
+            })
+        })
+        .collect()
+}
+
+fn positions(mesh: &Mesh) -> Vec<[f32; 3]> {
+    mesh.vertices().iter().map(|p| [p.x, p.y, p.z]).collect()
+}
+
+#[test]
+fn raises_each_pixel_by_its_brightness() {
+    let mesh = Mesh::from_heightmap(&heightmap(), SPACING, HEIGHT_SCALE);
+
+    assert_eq!(mesh.vertex_count(), 16);
+    // 3x3 cells of two triangles each
+    assert_eq!(mesh.face_count(), 18);
+
+    for (j, row) in mesh.vertices().chunks(4).enumerate() {
+        for (i, vertex) in row.iter().enumerate() {
+            let height = (i + j * 4) as f32 * 17.0 / 255.0 * HEIGHT_SCALE;
+            assert_eq!(vertex.x, (i as f32 - 1.5) * SPACING);
+            assert_eq!(vertex.z, (j as f32 - 1.5) * SPACING);
+            assert!(
+                (vertex.y - height).abs() < 1e-3,
+                "pixel ({i}, {j}) is at {} instead of {height}",
+                vertex.y
+            );
+        }
+    }
+
+    for (i, face) in mesh.faces().iter().enumerate() {
+        let [a, b, c] = face.vertices.map(|v| mesh.vertices()[v]);
+        let normal = cross_product(&vec_sub(&b, &a), &vec_sub(&c, &a));
+        assert!(normal.y > 0.0, "face {i} faces down");
+    }
+    for (position, [u, v]) in corners(&mesh) {
+        assert!(
+            (0.0..=1.0).contains(&u) && (0.0..=1.0).contains(&v),
+            "({u}, {v}) at {position:?} is off the texture"
+        );
+    }
+}
+
+#[test]
+fn chunks_line_up_with_the_whole_terrain() {
+    let image = heightmap();
+    let whole = Mesh::from_heightmap(&image, SPACING, HEIGHT_SCALE);
+    let chunks = Mesh::heightmap_chunks(&image, SPACING, HEIGHT_SCALE, 2);
+
+    // 3x3 cells in chunks of up to 2x2 -> 2x2, 1x2, 2x1 and 1x1
+    let face_counts: Vec<_> = chunks.iter().map(Mesh::face_count).collect();
+    assert_eq!(face_counts, [8, 4, 4, 2]);
+
+    // Every corner is where it is on the whole terrain, with the same texture coordinates, so
+    // neither the surface nor the texture has a seam between chunks
+    let whole_corners = corners(&whole);
+    for (index, chunk) in chunks.iter().enumerate() {
+        for (position, uv) in corners(chunk) {
+            assert!(
+                whole_corners.contains(&(position, uv)),
+                "chunk {index} has a corner at {position:?} {uv:?} that isn't on the terrain"
+            );
+        }
+    }
+
+    // Neighbours share the vertices along their edge -> The column at x = 2 and the row at z = 2
+    let shared = |a: &Mesh, b: &Mesh| {
+        let b = positions(b);
+        positions(a)
+            .iter()
+            .filter(|position| b.contains(position))
+            .count()
+    };
+    assert_eq!(shared(&chunks[0], &chunks[1]), 3);
+    assert_eq!(shared(&chunks[0], &chunks[2]), 3);
+    assert_eq!(shared(&chunks[1], &chunks[3]), 2);
+    assert_eq!(shared(&chunks[0], &chunks[3]), 1);
+}
+
+#[test]
+fn images_under_two_by_two_have_no_cells() {
+    for (width, height) in [(1, 1), (1, 5), (5, 1)] {
+        let image = Image::gen_image_color(width, height, BLACK);
+
+        let mesh = Mesh::from_heightmap(&image, SPACING, HEIGHT_SCALE);
+        assert_eq!((mesh.vertex_count(), mesh.face_count()), (0, 0));
+        assert!(Mesh::heightmap_chunks(&image, SPACING, HEIGHT_SCALE, 2).is_empty());
+    }
+}